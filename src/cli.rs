@@ -0,0 +1,269 @@
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use webserver::log::Level;
+
+pub const USAGE: &str = "\
+Usage: webserver [OPTIONS] [ROOT]
+
+Serve the files of ROOT, the current directory by default, over HTTP.
+
+Options:
+  -c, --config <FILE>      Read the settings from FILE, which can't be combined
+                           with the other options
+  -b, --bind <ADDRESS>     Address to listen on, with or without a port; can be
+                           repeated [default: 127.0.0.1]
+  -p, --port <PORT>        Port of the addresses given without one
+                           [default: 8080]
+  -w, --workers <COUNT>    Number of worker threads [default: number of CPUs]
+  -l, --log-level <LEVEL>  One of off, error, warn, info or debug
+                           [default: info]
+      --no-listing         Forbid directories instead of listing their entries
+      --hidden             List and serve hidden files
+      --uploads            Accept file uploads with PUT requests
+  -h, --help               Print this help
+";
+
+/// What the command line asks for.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve(Args),
+    Help,
+}
+
+/// Settings given on the command line.
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub config: Option<PathBuf>,
+    pub root: PathBuf,
+    pub bind: Vec<String>,
+    pub port: u16,
+    pub workers: Option<usize>,
+    pub log_level: Level,
+    pub listing: bool,
+    pub hidden_files: bool,
+    pub uploads: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            config: None,
+            root: PathBuf::from("."),
+            bind: Vec::new(),
+            port: 8080,
+            workers: None,
+            log_level: Level::Info,
+            listing: true,
+            hidden_files: false,
+            uploads: false,
+        }
+    }
+}
+
+impl Args {
+    /// Parse the command line arguments, program name excluded.
+    ///
+    /// Options taking a value accept it either as the next argument or after
+    /// an `=`, as in `--port=80`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+        let mut parsed = Args::default();
+        let mut root = None;
+        // First argument other than the configuration file
+        let mut other = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("missing value for '{}'", name))
+            };
+            let invalid = |value: &str| format!("invalid value '{}' for '{}'", value, name);
+            if !matches!(name, "-h" | "--help" | "-c" | "--config") && other.is_none() {
+                other = Some(name.to_string());
+            }
+            match name {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-c" | "--config" => parsed.config = Some(PathBuf::from(value()?)),
+                "-b" | "--bind" => parsed.bind.push(value()?),
+                "-p" | "--port" => {
+                    let value = value()?;
+                    parsed.port = value.parse().map_err(|_| invalid(&value))?;
+                }
+                "-w" | "--workers" => {
+                    let value = value()?;
+                    parsed.workers = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|workers| *workers > 0)
+                            .ok_or_else(|| invalid(&value))?,
+                    );
+                }
+                "-l" | "--log-level" => {
+                    let value = value()?;
+                    parsed.log_level = value.parse().map_err(|_| invalid(&value))?;
+                }
+                "--no-listing" => parsed.listing = false,
+                "--hidden" => parsed.hidden_files = true,
+                "--uploads" => parsed.uploads = true,
+                _ if name.starts_with('-') && name != "-" => {
+                    return Err(format!("unexpected option '{}'", name))
+                }
+                _ if root.is_none() => root = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument '{}'", arg)),
+            }
+        }
+        if let (Some(_), Some(other)) = (&parsed.config, other) {
+            return Err(format!("'{}' can't be combined with '--config'", other));
+        }
+        if let Some(root) = root {
+            parsed.root = root;
+        }
+        Ok(Command::Serve(parsed))
+    }
+
+    /// Resolve the addresses to listen on, using the port option for those
+    /// given without one.
+    pub fn addresses(&self) -> Result<Vec<SocketAddr>, String> {
+        let default = [String::from("127.0.0.1")];
+        let bind = if self.bind.is_empty() {
+            &default[..]
+        } else {
+            &self.bind[..]
+        };
+        let mut addresses = Vec::new();
+        for address in bind {
+            let resolved = if let Ok(address) = address.parse::<SocketAddr>() {
+                vec![address]
+            } else if let Ok(ip) = address.parse::<IpAddr>() {
+                vec![SocketAddr::new(ip, self.port)]
+            } else {
+                address
+                    .to_socket_addrs()
+                    .or_else(|_| (address.as_str(), self.port).to_socket_addrs())
+                    .map_err(|e| format!("couldn't resolve '{}': {}", address, e))?
+                    .collect()
+            };
+            addresses.extend(resolved);
+        }
+        Ok(addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_args(args: &[&str]) -> Args {
+        match parse(args).unwrap() {
+            Command::Serve(args) => args,
+            Command::Help => panic!("expected arguments to serve"),
+        }
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let args = parse_args(&[]);
+        assert_eq!(Args::default(), args);
+        assert_eq!(
+            vec!["127.0.0.1:8080".parse::<SocketAddr>().unwrap()],
+            args.addresses().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse_args(&[
+            "--bind",
+            "0.0.0.0",
+            "-b",
+            "[::1]:9000",
+            "--port=80",
+            "-w",
+            "3",
+            "--log-level",
+            "debug",
+            "--no-listing",
+            "--hidden",
+            "--uploads",
+            "/srv/www",
+        ]);
+        assert_eq!(
+            Args {
+                config: None,
+                root: PathBuf::from("/srv/www"),
+                bind: vec![String::from("0.0.0.0"), String::from("[::1]:9000")],
+                port: 80,
+                workers: Some(3),
+                log_level: Level::Debug,
+                listing: false,
+                hidden_files: true,
+                uploads: true,
+            },
+            args
+        );
+        assert_eq!(
+            vec![
+                "0.0.0.0:80".parse::<SocketAddr>().unwrap(),
+                "[::1]:9000".parse().unwrap()
+            ],
+            args.addresses().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(
+            Some(PathBuf::from("webserver.toml")),
+            parse_args(&["--config=webserver.toml"]).config
+        );
+        assert_eq!(
+            Err(String::from(
+                "'--uploads' can't be combined with '--config'"
+            )),
+            parse(&["--uploads", "-c", "webserver.toml"])
+        );
+        assert_eq!(
+            Err(String::from("'/srv/www' can't be combined with '--config'")),
+            parse(&["-c", "webserver.toml", "/srv/www"])
+        );
+    }
+
+    #[test]
+    fn test_parse_help() {
+        assert_eq!(Command::Help, parse(&["--port", "80", "-h"]).unwrap());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Err(String::from("missing value for '--port'")),
+            parse(&["--port"])
+        );
+        assert_eq!(
+            Err(String::from("invalid value '0' for '--workers'")),
+            parse(&["--workers=0"])
+        );
+        assert_eq!(
+            Err(String::from("invalid value 'loud' for '-l'")),
+            parse(&["-l", "loud"])
+        );
+        assert_eq!(
+            Err(String::from("unexpected option '--verbose'")),
+            parse(&["--verbose"])
+        );
+        assert_eq!(
+            Err(String::from("unexpected argument 'b'")),
+            parse(&["a", "b"])
+        );
+    }
+}
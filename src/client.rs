@@ -0,0 +1,431 @@
+use crate::http::message::ParseOptions;
+use crate::http::url::Form;
+use crate::http::{Error, Message, Method, Result, StartLine, Status, Url, Version};
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Blocking HTTP/1.1 client, reusing persistent connections to each server.
+#[derive(Default)]
+pub struct Client {
+    connections: HashMap<String, BufReader<TcpStream>>,
+    max_redirects: usize,
+    timeout: Option<Duration>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow at most `max_redirects` redirections, none by default.
+    pub fn set_max_redirects(&mut self, max_redirects: usize) {
+        self.max_redirects = max_redirects;
+    }
+
+    /// Set the timeout of every read and write on the connections, none by
+    /// default.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Send a GET request to `url`.
+    pub fn get(&mut self, url: &str) -> Result<Message> {
+        self.send(Message::request(Method::Get, url).build()?)
+    }
+
+    /// Send `request`, whose url must be an absolute `http` url, and return
+    /// the response.
+    ///
+    /// Redirections are followed if enabled, except those that require sending
+    /// the request body again.
+    pub fn send(&mut self, mut request: Message) -> Result<Message> {
+        let mut redirects = 0;
+        loop {
+            let (method, url) = match request.start_line() {
+                StartLine::Request { method, url, .. } => (*method, url.clone()),
+                StartLine::Response { .. } => {
+                    return Err(Error::Client(String::from(
+                        "expected a request instead of a response",
+                    )))
+                }
+            };
+            let headers = request.headers().clone();
+            let has_body = request.body().is_some();
+            let response = self.send_once(request)?;
+            if redirects == self.max_redirects {
+                return Ok(response);
+            }
+            let (status, location) = match (response.status(), response.headers().get_location()?) {
+                (Some(status), Some(location)) => (status, location),
+                _ => return Ok(response),
+            };
+            // See RFC 9110 section 15.4 for the method used after a redirection
+            let redirect_method = match status {
+                Status::SeeOther if method != Method::Head => Method::Get,
+                Status::MovedPermanently | Status::Found if method == Method::Post => Method::Get,
+                Status::MovedPermanently
+                | Status::Found
+                | Status::SeeOther
+                | Status::TemporaryRedirect
+                | Status::PermanentRedirect => method,
+                _ => return Ok(response),
+            };
+            if redirect_method == method && has_body {
+                return Ok(response);
+            }
+            let mut headers = headers;
+            if redirect_method != method {
+                for name in ["Content-Length", "Transfer-Encoding", "Content-Type"] {
+                    headers.remove(name);
+                }
+            }
+            let location = resolve_location(&url, location)?;
+            let same_authority = match (url.authority(), location.authority()) {
+                (Some(from), Some(to)) => {
+                    strip_userinfo(from).eq_ignore_ascii_case(strip_userinfo(to))
+                }
+                _ => false,
+            };
+            if !same_authority {
+                // Credentials are only meant for the server they were given to
+                for name in ["Authorization", "Cookie", "Proxy-Authorization"] {
+                    headers.remove(name);
+                }
+            }
+            request = Message::new_request(redirect_method, location, Some(headers), None);
+            redirects += 1;
+        }
+    }
+
+    /// Send `request` to the server of its url, without following
+    /// redirections.
+    fn send_once(&mut self, mut request: Message) -> Result<Message> {
+        let (method, url) = match request.start_line() {
+            StartLine::Request { method, url, .. } => (*method, url.clone()),
+            StartLine::Response { .. } => unreachable!("checked by Client::send"),
+        };
+        let authority = match (url.form(), url.scheme(), url.authority()) {
+            (Form::Absolute, Some("http"), Some(authority)) => {
+                strip_userinfo(authority).to_string()
+            }
+            _ => {
+                return Err(Error::Client(format!(
+                    "expected an absolute http url instead of '{}'",
+                    url
+                )))
+            }
+        };
+        request.headers_mut().set("Host", authority.as_str());
+        *request.start_line_mut() = StartLine::Request {
+            method,
+            url: url.origin_form(),
+            version: Version::V1_1,
+        };
+
+        let mut reader = self.connect(&authority)?;
+        let mut writer = BufWriter::new(reader.get_ref());
+        request.write(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+
+        // Interim responses are skipped, as nothing is done about them
+        let mut response = loop {
+            let response = Message::read_head(&mut reader)?;
+            match response.status() {
+                Some(status) if status.is_informational() => continue,
+                _ => break response,
+            }
+        };
+        if method != Method::Head {
+            // Responses are as large as the resources asked for, so their body
+            // isn't limited like that of requests
+            let options = ParseOptions {
+                max_body_size: usize::MAX,
+                ..ParseOptions::default()
+            };
+            response.read_body_with(&mut reader, &options)?;
+        }
+
+        let delimited = response.body().is_none()
+            || response.headers().is_chunked()
+            || matches!(response.headers().get_content_length(), Ok(Some(_)));
+        if request.keep_alive() && response.keep_alive() && delimited {
+            self.connections.insert(authority, reader);
+        }
+        Ok(response)
+    }
+
+    /// Return a connection to `authority`, reusing the last one if it is still
+    /// open.
+    fn connect(&mut self, authority: &str) -> Result<BufReader<TcpStream>> {
+        if let Some(reader) = self.connections.remove(authority) {
+            if reader.buffer().is_empty() && is_open(reader.get_ref()) {
+                return Ok(reader);
+            }
+        }
+        let has_port = !authority.ends_with(']')
+            && authority.rsplit_once(':').is_some_and(|(_, port)| {
+                !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit())
+            });
+        let stream = if has_port {
+            TcpStream::connect(authority)?
+        } else {
+            TcpStream::connect(format!("{}:80", authority))?
+        };
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+        Ok(BufReader::new(stream))
+    }
+}
+
+/// Return the host and optional port of `authority`, without the user
+/// information that may precede them.
+fn strip_userinfo(authority: &str) -> &str {
+    authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+}
+
+/// Return whether the server didn't close an idle connection, in which case
+/// nothing can be read from it yet.
+fn is_open(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let open = matches!(stream.peek(&mut [0]), Err(e) if e.kind() == ErrorKind::WouldBlock);
+    stream.set_nonblocking(false).is_ok() && open
+}
+
+/// Resolve the `Location` of a redirection, either an absolute url or an
+/// absolute path on the server of `url`.
+fn resolve_location(url: &Url, location: Url) -> Result<Url> {
+    match location.form() {
+        Form::Origin => format!(
+            "{}://{}{}",
+            url.scheme().unwrap_or("http"),
+            url.authority().unwrap_or_default(),
+            location
+        )
+        .parse(),
+        _ => Ok(location),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Body, Headers};
+    use crate::server::{connection, Config, Server};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    fn setup_server() -> SocketAddr {
+        setup_server_with_config(Config::default())
+    }
+
+    fn setup_server_with_config(config: Config) -> SocketAddr {
+        let server = Server::bind(
+            "127.0.0.1:0",
+            Config {
+                workers: 2,
+                queue_size: 2,
+                ..config
+            },
+        )
+        .unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run(handle_request));
+        address
+    }
+
+    fn handle_request(request: &Message) -> Result<Message> {
+        let url = match request.start_line() {
+            StartLine::Request { url, .. } => url,
+            _ => unreachable!(),
+        };
+        match url.path() {
+            "/redirect" => Ok(Message::new(
+                Status::Found,
+                Some(Headers::from([(
+                    String::from("Location"),
+                    url.query_param("to")
+                        .unwrap_or_else(|| String::from("/hello")),
+                )])),
+                Some(Body::from(Vec::new())),
+            )),
+            "/credentials" => Ok(Message::new(
+                Status::Ok,
+                None,
+                Some(Body::from(
+                    ["Authorization", "Cookie", "Proxy-Authorization"]
+                        .map(|name| request.headers().get(name).unwrap_or_default())
+                        .join(","),
+                )),
+            )),
+            "/chunked" => Ok(Message::new(
+                Status::Ok,
+                None,
+                Some(Body::from_reader("hello".as_bytes(), None)),
+            )),
+            "/host" => Ok(Message::new(
+                Status::Ok,
+                None,
+                Some(request.headers().get("Host").unwrap_or_default().parse()?),
+            )),
+            _ => Ok(Message::new(Status::Ok, None, Some("hello".parse()?))),
+        }
+    }
+
+    fn body(response: &Message) -> String {
+        response.body().as_ref().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_get() {
+        let address = setup_server();
+        let response = Client::new()
+            .get(&format!("http://{}/hello", address))
+            .unwrap();
+        assert_eq!(Some(Status::Ok), response.status());
+        assert_eq!("hello", body(&response));
+    }
+
+    #[test]
+    fn test_get_sends_host() {
+        let address = setup_server();
+        let response = Client::new()
+            .get(&format!("http://{}/host", address))
+            .unwrap();
+        assert_eq!(address.to_string(), body(&response));
+    }
+
+    #[test]
+    fn test_get_sends_host_without_userinfo() {
+        let address = setup_server();
+        let response = Client::new()
+            .get(&format!("http://user:secret@{}/host", address))
+            .unwrap();
+        assert_eq!(address.to_string(), body(&response));
+    }
+
+    #[test]
+    fn test_get_chunked() {
+        let address = setup_server();
+        let response = Client::new()
+            .get(&format!("http://{}/chunked", address))
+            .unwrap();
+        assert!(response.headers().is_chunked());
+        assert_eq!("hello", body(&response));
+    }
+
+    #[test]
+    fn test_get_until_close() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            Message::read(&mut BufReader::new(&stream)).unwrap();
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\n\r\nhello until close")
+                .unwrap();
+        });
+        let response = Client::new().get(&format!("http://{}/", address)).unwrap();
+        assert_eq!("hello until close", body(&response));
+    }
+
+    #[test]
+    fn test_send_head() {
+        let address = setup_server();
+        let response = Client::new()
+            .send(
+                Message::request(Method::Head, format!("http://{}/hello", address))
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(Some(5), response.headers().get_content_length().unwrap());
+        assert_eq!(&None, response.body());
+    }
+
+    #[test]
+    fn test_get_reuses_connection() {
+        // Only a single connection is accepted
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            connection::handle(
+                stream,
+                &Config::default(),
+                &handle_request,
+                &AtomicBool::new(false),
+            )
+            .ok();
+        });
+        let mut client = Client::new();
+        client.set_timeout(Some(Duration::from_secs(1)));
+        for _ in 0..3 {
+            let response = client.get(&format!("http://{}/hello", address)).unwrap();
+            assert_eq!("hello", body(&response));
+        }
+    }
+
+    #[test]
+    fn test_get_reconnects_after_server_closed_idle_connection() {
+        let address = setup_server_with_config(Config {
+            keep_alive_timeout: Duration::from_millis(10),
+            ..Config::default()
+        });
+        let url = format!("http://{}/hello", address);
+        let mut client = Client::new();
+        assert_eq!("hello", body(&client.get(&url).unwrap()));
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!("hello", body(&client.get(&url).unwrap()));
+    }
+
+    #[test]
+    fn test_get_follows_redirects() {
+        let address = setup_server();
+        let url = format!("http://{}/redirect", address);
+        let mut client = Client::new();
+        assert_eq!(Some(Status::Found), client.get(&url).unwrap().status());
+        client.set_max_redirects(1);
+        let response = client.get(&url).unwrap();
+        assert_eq!(Some(Status::Ok), response.status());
+        assert_eq!("hello", body(&response));
+    }
+
+    #[test]
+    fn test_send_drops_credentials_on_redirect_to_other_authority() {
+        let address = setup_server();
+        let send = |to: &str| {
+            let mut client = Client::new();
+            client.set_max_redirects(1);
+            let request = Message::request(
+                Method::Get,
+                format!("http://{}/redirect?to={}", address, to),
+            )
+            .header("Authorization", "Basic dXNlcjpzZWNyZXQ=")
+            .header("Cookie", "session=1")
+            .header("Proxy-Authorization", "Basic cHJveHk6c2VjcmV0")
+            .build()
+            .unwrap();
+            body(&client.send(request).unwrap())
+        };
+        assert_eq!(
+            "Basic dXNlcjpzZWNyZXQ=,session=1,Basic cHJveHk6c2VjcmV0",
+            send("/credentials")
+        );
+        let elsewhere = format!("http://localhost:{}/credentials", address.port());
+        assert_eq!(",,", send(&elsewhere));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected an absolute http url")]
+    fn test_get_panic_if_url_is_not_absolute() {
+        Client::new().get("/hello").unwrap();
+    }
+}
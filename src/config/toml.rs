@@ -0,0 +1,768 @@
+//! Parser for the subset of [TOML 1.0](https://toml.io/en/v1.0.0) used by
+//! configuration files. Every value keeps the line it was defined on, so that
+//! the configuration built from it can point at the offending line, which
+//! parsers meant for deserialization, such as the `toml` crate, don't expose
+//! without pulling in `serde`.
+//!
+//! The subset is made of:
+//!
+//! - comments, and LF or CRLF line endings;
+//! - bare, quoted and dotted keys;
+//! - `[table]` and `[[array.of.tables]]` headers;
+//! - basic strings with every escape sequence, and literal strings, both on a
+//!   single line;
+//! - decimal integers, with an optional sign and underscores between digits;
+//! - `true` and `false`;
+//! - arrays, which may span several lines and end with a comma;
+//! - inline tables, on a single line.
+//!
+//! Multi-line strings, floats, dates and times, and hexadecimal, octal and
+//! binary integers are valid TOML left out of the subset, and are reported as
+//! unsupported rather than as syntax errors.
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// Error met while parsing or interpreting a document, at a 1-based line.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Item>),
+    Table(Table),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Table(_) => "a table",
+        }
+    }
+}
+
+/// Value along with the line it was defined on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub value: Value,
+    pub line: usize,
+}
+
+impl Item {
+    fn invalid_type(&self, key: &str, expected: &str) -> Error {
+        Error::new(
+            self.line,
+            format!(
+                "'{}' should be {} instead of {}",
+                key,
+                expected,
+                self.value.type_name()
+            ),
+        )
+    }
+
+    pub fn as_str(&self, key: &str) -> Result<&str> {
+        match &self.value {
+            Value::String(s) => Ok(s),
+            _ => Err(self.invalid_type(key, "a string")),
+        }
+    }
+
+    pub fn as_integer(&self, key: &str) -> Result<i64> {
+        match &self.value {
+            Value::Integer(i) => Ok(*i),
+            _ => Err(self.invalid_type(key, "an integer")),
+        }
+    }
+
+    pub fn as_bool(&self, key: &str) -> Result<bool> {
+        match &self.value {
+            Value::Boolean(b) => Ok(*b),
+            _ => Err(self.invalid_type(key, "a boolean")),
+        }
+    }
+
+    pub fn as_array(&self, key: &str) -> Result<&[Item]> {
+        match &self.value {
+            Value::Array(items) => Ok(items),
+            _ => Err(self.invalid_type(key, "an array")),
+        }
+    }
+
+    pub fn as_table(&self, key: &str) -> Result<&Table> {
+        match &self.value {
+            Value::Table(table) => Ok(table),
+            _ => Err(self.invalid_type(key, "a table")),
+        }
+    }
+
+    /// Return the strings of an array, failing on any other element.
+    pub fn as_strings(&self, key: &str) -> Result<Vec<&str>> {
+        self.as_array(key)?
+            .iter()
+            .map(|item| item.as_str(key))
+            .collect()
+    }
+}
+
+/// Key/value pairs in the order they were defined.
+pub type Table = Vec<(String, Item)>;
+
+/// Parse a whole document into its root table.
+pub fn parse(document: &str) -> Result<Table> {
+    Parser {
+        chars: document.chars().collect(),
+        position: 0,
+        line: 1,
+    }
+    .document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::new(self.line, message)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.next();
+                Ok(())
+            }
+            Some('\n') | None => Err(self.error(format!("expected '{}'", expected))),
+            Some(c) => Err(self.error(format!("expected '{}' instead of '{}'", expected, c))),
+        }
+    }
+
+    /// Skip spaces and tabs.
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.next();
+        }
+    }
+
+    /// Skip whitespace, comments and line endings, as allowed between array
+    /// elements or between statements.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('#') => self.skip_comment(),
+                Some('\r' | '\n') => {
+                    self.next();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while !matches!(self.peek(), Some('\n') | None) {
+            self.next();
+        }
+    }
+
+    /// Consume the end of a statement: whitespace, an optional comment, and
+    /// the line ending.
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() == Some('#') {
+            self.skip_comment();
+        }
+        if self.peek() == Some('\r') {
+            self.next();
+        }
+        match self.peek() {
+            Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            None => Ok(()),
+            Some(c) => Err(self.error(format!("unexpected '{}' at the end of the line", c))),
+        }
+    }
+
+    fn document(mut self) -> Result<Table> {
+        let mut root = Table::new();
+        let mut current = Vec::new();
+        // Tables given a header, identified by their path with the index of
+        // the arrays of tables gone through
+        let mut defined = HashSet::new();
+        loop {
+            self.skip_blank();
+            let line = self.line;
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.next();
+                    let array = self.peek() == Some('[');
+                    if array {
+                        self.next();
+                    }
+                    self.skip_whitespace();
+                    let path = self.key()?;
+                    self.skip_whitespace();
+                    self.expect(']')?;
+                    if array {
+                        self.expect(']')?;
+                    }
+                    let (parent, name) = path.split_at(path.len() - 1);
+                    let (table, mut id) = table_at(&mut root, parent, line)?;
+                    if array {
+                        push_table(table, &name[0], line)?;
+                    }
+                    id.extend(table_at(table, name, line)?.1);
+                    if !defined.insert(id) {
+                        return Err(Error::new(
+                            line,
+                            format!("table '{}' is defined twice", path.join(".")),
+                        ));
+                    }
+                    current = path;
+                }
+                Some(_) => {
+                    let (path, item) = self.key_value()?;
+                    let (table, _) = table_at(&mut root, &current, line)?;
+                    insert(table, &path, item)?;
+                }
+            }
+            self.end_of_line()?;
+        }
+    }
+
+    /// Parse a dotted key into its parts.
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut parts = vec![self.simple_key()?];
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                return Ok(parts);
+            }
+            self.next();
+            self.skip_whitespace();
+            parts.push(self.simple_key()?);
+        }
+    }
+
+    fn simple_key(&mut self) -> Result<String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.position;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.next();
+                }
+                if start == self.position {
+                    return Err(self.error("expected a key"));
+                }
+                Ok(self.chars[start..self.position].iter().collect())
+            }
+        }
+    }
+
+    fn key_value(&mut self) -> Result<(Vec<String>, Item)> {
+        let path = self.key()?;
+        self.skip_whitespace();
+        self.expect('=')?;
+        self.skip_whitespace();
+        let item = self.value()?;
+        Ok((path, item))
+    }
+
+    fn value(&mut self) -> Result<Item> {
+        let line = self.line;
+        let quotes = |quote| self.chars[self.position..].starts_with(&[quote; 3]);
+        if quotes('"') || quotes('\'') {
+            return Err(self.error("multi-line strings aren't supported"));
+        }
+        let value = match self.peek() {
+            Some('"') => Value::String(self.basic_string()?),
+            Some('\'') => Value::String(self.literal_string()?),
+            Some('[') => Value::Array(self.array()?),
+            Some('{') => Value::Table(self.inline_table()?),
+            _ => {
+                let start = self.position;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || "+-_.:".contains(c))
+                {
+                    self.next();
+                }
+                let word: String = self.chars[start..self.position].iter().collect();
+                match word.as_str() {
+                    "true" => Value::Boolean(true),
+                    "false" => Value::Boolean(false),
+                    "" => return Err(self.error("expected a value")),
+                    _ => Value::Integer(
+                        parse_integer(&word)
+                            .ok_or_else(|| Error::new(line, invalid_value(&word)))?,
+                    ),
+                }
+            }
+        };
+        Ok(Item { value, line })
+    }
+
+    fn basic_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let Some(c) = self.peek().filter(|c| *c != '\n') else {
+                return Err(self.error("unterminated string"));
+            };
+            self.next();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape(4)?,
+                        Some('U') => self.unicode_escape(8)?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    s.push(c);
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char> {
+        let mut code = 0;
+        for _ in 0..digits {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            let Some(c) = self.peek().filter(|c| *c != '\n') else {
+                return Err(self.error("unterminated string"));
+            };
+            self.next();
+            match c {
+                '\'' => return Ok(s),
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Parse an array, which may span several lines and end with a comma.
+    fn array(&mut self) -> Result<Vec<Item>> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.next();
+                return Ok(items);
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                _ => {
+                    self.expect(']')?;
+                    return Ok(items);
+                }
+            }
+        }
+    }
+
+    /// Parse an inline table, which must fit on a single line.
+    fn inline_table(&mut self) -> Result<Table> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(table);
+        }
+        loop {
+            self.skip_whitespace();
+            let (path, item) = self.key_value()?;
+            insert(&mut table, &path, item)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                _ => {
+                    self.expect('}')?;
+                    return Ok(table);
+                }
+            }
+        }
+    }
+}
+
+fn parse_integer(word: &str) -> Option<i64> {
+    let digits = word.strip_prefix(['+', '-']).unwrap_or(word);
+    let valid = !digits.is_empty()
+        && digits.bytes().all(|b| b.is_ascii_digit() || b == b'_')
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && !digits.contains("__");
+    if !valid {
+        return None;
+    }
+    word.replace('_', "").parse().ok()
+}
+
+/// Describe why `word` isn't a value, telling apart the valid TOML left out of
+/// the subset.
+fn invalid_value(word: &str) -> String {
+    let unsigned = word.strip_prefix(['+', '-']).unwrap_or(word);
+    let kind = if ["0x", "0o", "0b"].iter().any(|p| unsigned.starts_with(p)) {
+        "non-decimal integers"
+    } else if ["inf", "nan"].contains(&unsigned)
+        || unsigned.starts_with(|c: char| c.is_ascii_digit())
+            && unsigned.contains(['.', 'e', 'E'])
+            && !unsigned.contains(':')
+    {
+        "floats"
+    } else if unsigned.starts_with(|c: char| c.is_ascii_digit())
+        && (unsigned.contains(':') || unsigned[1..].contains('-'))
+    {
+        "dates and times"
+    } else if unsigned.bytes().all(|b| b.is_ascii_digit() || b == b'_') && !unsigned.is_empty() {
+        return format!("integer '{}' is out of range", word);
+    } else {
+        return format!("invalid value '{}'", word);
+    };
+    format!("{} aren't supported, as in '{}'", kind, word)
+}
+
+fn get_mut<'a>(table: &'a mut Table, key: &str) -> Option<&'a mut Item> {
+    table
+        .iter_mut()
+        .find(|(k, _)| k == key)
+        .map(|(_, item)| item)
+}
+
+/// Return the table at `path`, creating the missing ones, along with its
+/// identifier. Arrays of tables are walked through their last element.
+fn table_at<'a>(
+    mut table: &'a mut Table,
+    path: &[String],
+    line: usize,
+) -> Result<(&'a mut Table, Vec<String>)> {
+    let mut id = Vec::new();
+    for key in path {
+        if get_mut(table, key).is_none() {
+            table.push((
+                key.clone(),
+                Item {
+                    value: Value::Table(Table::new()),
+                    line,
+                },
+            ));
+        }
+        id.push(key.clone());
+        table = match &mut get_mut(table, key).unwrap().value {
+            Value::Table(table) => table,
+            Value::Array(items) if !items.is_empty() => {
+                id.push((items.len() - 1).to_string());
+                match &mut items.last_mut().unwrap().value {
+                    Value::Table(table) => table,
+                    _ => return Err(Error::new(line, format!("'{}' is not a table", key))),
+                }
+            }
+            _ => return Err(Error::new(line, format!("'{}' is not a table", key))),
+        };
+    }
+    Ok((table, id))
+}
+
+/// Append a new table to the array of tables `key`.
+fn push_table(table: &mut Table, key: &str, line: usize) -> Result<()> {
+    let new = Item {
+        value: Value::Table(Table::new()),
+        line,
+    };
+    match get_mut(table, key) {
+        None => table.push((
+            key.to_string(),
+            Item {
+                value: Value::Array(vec![new]),
+                line,
+            },
+        )),
+        Some(Item {
+            value: Value::Array(items),
+            ..
+        }) if items
+            .iter()
+            .all(|item| matches!(item.value, Value::Table(_))) =>
+        {
+            items.push(new)
+        }
+        Some(_) => {
+            return Err(Error::new(
+                line,
+                format!("'{}' is not an array of tables", key),
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Insert `item` at the dotted key `path`, creating intermediate tables.
+fn insert(table: &mut Table, path: &[String], item: Item) -> Result<()> {
+    let line = item.line;
+    let (parent, key) = path.split_at(path.len() - 1);
+    let (table, _) = table_at(table, parent, line)?;
+    if get_mut(table, &key[0]).is_some() {
+        return Err(Error::new(
+            line,
+            format!("key '{}' is defined twice", path.join(".")),
+        ));
+    }
+    table.push((key[0].clone(), item));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(value: Value, line: usize) -> Item {
+        Item { value, line }
+    }
+
+    fn string(s: &str, line: usize) -> Item {
+        item(Value::String(s.to_string()), line)
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let table = parse(
+            "\
+# A comment
+name = \"a \\\"quoted\\\" \\u00e9\"  # trailing comment
+path = 'C:\\www'
+port = 8_080
+negative = -1
+enabled = true
+list = [
+    1,
+    2, # comment
+]
+inline = { a.b = false, \"c d\" = [] }
+",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                (String::from("name"), string("a \"quoted\" é", 2)),
+                (String::from("path"), string("C:\\www", 3)),
+                (String::from("port"), item(Value::Integer(8080), 4)),
+                (String::from("negative"), item(Value::Integer(-1), 5)),
+                (String::from("enabled"), item(Value::Boolean(true), 6)),
+                (
+                    String::from("list"),
+                    item(
+                        Value::Array(vec![item(Value::Integer(1), 8), item(Value::Integer(2), 9)]),
+                        7
+                    )
+                ),
+                (
+                    String::from("inline"),
+                    item(
+                        Value::Table(vec![
+                            (
+                                String::from("a"),
+                                item(
+                                    Value::Table(vec![(
+                                        String::from("b"),
+                                        item(Value::Boolean(false), 11)
+                                    )]),
+                                    11
+                                )
+                            ),
+                            (String::from("c d"), item(Value::Array(vec![]), 11)),
+                        ]),
+                        11
+                    )
+                ),
+            ],
+            table
+        );
+    }
+
+    #[test]
+    fn test_parse_tables() {
+        let table = parse(
+            "\
+[[site]]
+root = 'a'
+[site.errors]
+404 = 'missing.html'
+[[site.location]]
+path = '/x'
+[[site]]
+root = 'b'
+",
+        )
+        .unwrap();
+        let sites = table[0].1.as_array("site").unwrap();
+        assert_eq!(2, sites.len());
+        let first = sites[0].as_table("site").unwrap();
+        assert_eq!(1, sites[0].line);
+        assert_eq!(string("a", 2), first[0].1);
+        assert_eq!(
+            string("missing.html", 4),
+            first[1].1.as_table("errors").unwrap()[0].1
+        );
+        assert_eq!(1, first[2].1.as_array("location").unwrap().len());
+        assert_eq!(string("b", 8), sites[1].as_table("site").unwrap()[0].1);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Err(Error::new(2, "key 'a' is defined twice")),
+            parse("a = 1\na = 2")
+        );
+        assert_eq!(
+            Err(Error::new(3, "table 't' is defined twice")),
+            parse("[t]\n\n[t]")
+        );
+        assert_eq!(
+            Err(Error::new(1, "floats aren't supported, as in '1.5'")),
+            parse("a = 1.5")
+        );
+        assert_eq!(
+            Err(Error::new(1, "floats aren't supported, as in '-inf'")),
+            parse("a = -inf")
+        );
+        assert_eq!(
+            Err(Error::new(
+                1,
+                "dates and times aren't supported, as in '1979-05-27'"
+            )),
+            parse("a = 1979-05-27")
+        );
+        assert_eq!(
+            Err(Error::new(
+                1,
+                "dates and times aren't supported, as in '07:32:00'"
+            )),
+            parse("a = 07:32:00")
+        );
+        assert_eq!(
+            Err(Error::new(
+                1,
+                "non-decimal integers aren't supported, as in '0xff'"
+            )),
+            parse("a = 0xff")
+        );
+        assert_eq!(
+            Err(Error::new(
+                1,
+                "integer '99999999999999999999' is out of range"
+            )),
+            parse("a = 99999999999999999999")
+        );
+        assert_eq!(Err(Error::new(1, "invalid value 'yes'")), parse("a = yes"));
+        assert_eq!(
+            Err(Error::new(1, "multi-line strings aren't supported")),
+            parse("a = \"\"\"\nabc\"\"\"")
+        );
+        assert_eq!(
+            Err(Error::new(1, "multi-line strings aren't supported")),
+            parse("a = '''abc'''")
+        );
+        assert_eq!(
+            Err(Error::new(2, "unterminated string")),
+            parse("\na = \"abc\nb = 1")
+        );
+        assert_eq!(
+            Err(Error::new(1, "unexpected 'b' at the end of the line")),
+            parse("a = 1 b = 2")
+        );
+        assert_eq!(
+            Err(Error::new(2, "'a' is not an array of tables")),
+            parse("a = 1\n[[a]]")
+        );
+        assert_eq!(Err(Error::new(1, "expected '='")), parse("a\n= 1"));
+    }
+
+    #[test]
+    fn test_item_accessors() {
+        let item = string("x", 4);
+        assert_eq!("x", item.as_str("k").unwrap());
+        assert_eq!(
+            Err(Error::new(
+                4,
+                "'k' should be an integer instead of a string"
+            )),
+            item.as_integer("k")
+        );
+        assert_eq!(
+            "line 4: 'k' should be a boolean instead of a string",
+            item.as_bool("k").unwrap_err().to_string()
+        );
+    }
+}
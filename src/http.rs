@@ -1,20 +1,33 @@
 pub mod body;
+pub mod builder;
+pub mod cache_control;
+pub mod chunked;
+pub mod date;
 pub mod error;
 pub mod headers;
 pub mod index;
+pub mod media_type;
 pub mod message;
 pub mod method;
+pub mod mime;
+pub mod quality;
+pub mod range;
 pub mod status;
+pub mod url;
+pub mod validators;
 pub mod version;
 
 pub use body::Body;
+pub use builder::Builder;
+pub use cache_control::CacheControl;
 pub use error::Error;
 pub use error::Result;
 pub use headers::Headers;
+pub use media_type::MediaType;
 pub use message::Message;
 pub use message::StartLine;
 pub use method::Method;
+pub use quality::QualityItem;
 pub use status::Status;
+pub use url::Url;
 pub use version::Version;
-
-pub type Url = String;
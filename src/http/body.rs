@@ -1,48 +1,203 @@
-use super::{Error, Result};
-use std::io::BufRead;
-use std::io::Write;
+use super::message::ParseOptions;
+use super::{chunked, Error, Headers, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq)]
-pub struct Body(Vec<u8>);
+/// Capacity allocated upfront when reading a body of known length.
+const READ_CAPACITY: usize = 64 * 1024;
+
+/// Message body, either held in memory or streamed from a reader.
+pub struct Body(Content);
+
+enum Content {
+    Bytes(Vec<u8>),
+    Stream {
+        reader: Box<dyn Read + Send>,
+        length: Option<usize>,
+    },
+}
 
 impl Body {
+    /// Read a body of `content_length` bytes.
+    ///
+    /// Memory grows with the bytes actually received rather than with the
+    /// announced length, which the peer may lie about.
     pub fn read(bufread: &mut impl BufRead, content_length: usize) -> Result<Self> {
-        let mut body: Vec<u8> = Vec::with_capacity(content_length);
-        body.resize(content_length, 0);
+        let mut body = Vec::with_capacity(content_length.min(READ_CAPACITY));
+        bufread.take(content_length as u64).read_to_end(&mut body)?;
+        if body.len() != content_length {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "body ended after {} of {} bytes",
+                    body.len(),
+                    content_length
+                ),
+            )));
+        }
+        Ok(Body(Content::Bytes(body)))
+    }
+
+    /// Read a body delimited by the end of the stream, failing if it is larger
+    /// than `max_size`.
+    pub fn read_to_end(bufread: &mut impl BufRead, max_size: usize) -> Result<Self> {
+        let mut body = Vec::new();
         bufread
-            .read_exact(&mut body[..])
-            .map_err(|e| Error::Io(e))?;
-        Ok(Body(body))
+            .take(max_size.saturating_add(1) as u64)
+            .read_to_end(&mut body)?;
+        if body.len() > max_size {
+            return Err(Error::ContentTooLarge(format!(
+                "body is larger than {} bytes",
+                max_size
+            )));
+        }
+        Ok(Body(Content::Bytes(body)))
+    }
+
+    /// Read a body with the chunked transfer coding, returning it along with
+    /// its trailer fields.
+    pub fn read_chunked(
+        bufread: &mut impl BufRead,
+        options: &ParseOptions,
+    ) -> Result<(Self, Headers)> {
+        let (body, trailers) = chunked::read(bufread, options)?;
+        Ok((Body(Content::Bytes(body)), trailers))
+    }
+
+    /// Create a body streamed from `reader`, which yields `length` bytes if
+    /// known upfront.
+    pub fn from_reader(reader: impl Read + Send + 'static, length: Option<usize>) -> Self {
+        Body(Content::Stream {
+            reader: Box::new(reader),
+            length,
+        })
     }
 
-    pub fn write(&self, bufwrite: &mut impl Write) -> Result<()> {
-        bufwrite.write_fmt(format_args!("\r\n"))?;
-        bufwrite.write_all(&self.0)?;
+    /// Create a body streamed from `file`, whose length is its current size.
+    pub fn from_file(file: File) -> Result<Self> {
+        let length = file.metadata()?.len() as usize;
+        Ok(Self::from_reader(file, Some(length)))
+    }
+
+    /// Write the body, consuming the underlying reader of a streaming body.
+    pub fn write(&mut self, bufwrite: &mut impl Write) -> Result<()> {
+        match &mut self.0 {
+            Content::Bytes(bytes) => bufwrite.write_all(bytes)?,
+            Content::Stream {
+                reader,
+                length: Some(length),
+            } => {
+                let copied = io::copy(&mut reader.take(*length as u64), bufwrite)?;
+                if copied != *length as u64 {
+                    return Err(Error::Io(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("body stream ended after {} of {} bytes", copied, length),
+                    )));
+                }
+            }
+            Content::Stream {
+                reader,
+                length: None,
+            } => {
+                io::copy(reader, bufwrite)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the body with the chunked transfer coding, followed by
+    /// `trailers`.
+    pub fn write_chunked(&mut self, bufwrite: &mut impl Write, trailers: &Headers) -> Result<()> {
+        let mut writer = chunked::ChunkedWriter::new(bufwrite);
+        match &mut self.0 {
+            Content::Bytes(bytes) => writer.write_all(bytes)?,
+            Content::Stream { reader, .. } => {
+                io::copy(reader, &mut writer)?;
+            }
+        }
+        writer.finish(trailers)?;
         Ok(())
     }
 
-    pub fn len(&self) -> usize {
-        self.0.len()
+    /// Return the content of a body held in memory, `None` for a stream.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.0 {
+            Content::Bytes(bytes) => Some(bytes),
+            Content::Stream { .. } => None,
+        }
+    }
+
+    /// Return the length of the body, unknown for a stream created without
+    /// one.
+    pub fn len(&self) -> Option<usize> {
+        match &self.0 {
+            Content::Bytes(bytes) => Some(bytes.len()),
+            Content::Stream { length, .. } => *length,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
     }
 }
 
 impl FromStr for Body {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Body(Vec::from(s)))
+        Ok(Body::from(Vec::from(s)))
     }
 }
 
 impl From<Vec<u8>> for Body {
     fn from(vec: Vec<u8>) -> Self {
-        Body(vec)
+        Body(Content::Bytes(vec))
+    }
+}
+
+impl From<String> for Body {
+    fn from(s: String) -> Self {
+        Body::from(s.into_bytes())
+    }
+}
+
+impl From<&str> for Body {
+    fn from(s: &str) -> Self {
+        Body::from(Vec::from(s))
+    }
+}
+
+/// Streaming bodies are never equal, as comparing them would consume them.
+impl PartialEq for Body {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Content::Bytes(a), Content::Bytes(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Content::Bytes(bytes) => f.debug_tuple("Body").field(bytes).finish(),
+            Content::Stream { length, .. } => f
+                .debug_struct("Body")
+                .field("stream", &true)
+                .field("length", length)
+                .finish(),
+        }
     }
 }
 
-impl ToString for Body {
-    fn to_string(&self) -> String {
-        String::from_utf8_lossy(&self.0).to_string()
+/// Display the body as UTF-8 text, leaving streaming bodies aside.
+impl fmt::Display for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Content::Bytes(bytes) => write!(f, "{}", String::from_utf8_lossy(bytes)),
+            Content::Stream { .. } => Ok(()),
+        }
     }
 }
 
@@ -59,7 +214,7 @@ mod tests {
     }
 
     fn setup_body() -> Body {
-        Body(Vec::from(BODY_EXAMPLE.as_bytes()))
+        Body::from(Vec::from(BODY_EXAMPLE.as_bytes()))
     }
 
     #[test]
@@ -71,7 +226,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "failed to fill whole buffer")]
+    #[should_panic(expected = "body ended after 11 of 12 bytes")]
     fn test_read_panic_if_content_length_is_gt_than_read_from_buffer() {
         Body::read(&mut setup_buffer_reader(), BODY_EXAMPLE.len() + 1).unwrap();
     }
@@ -80,15 +235,90 @@ mod tests {
     fn test_write() {
         let mut buffer = Vec::new();
         setup_body().write(&mut buffer).unwrap();
+        assert_eq!(BODY_EXAMPLE, String::from_utf8_lossy(&buffer).to_string());
+    }
+
+    #[test]
+    fn test_read_to_end() {
         assert_eq!(
-            format!("\r\n{}", BODY_EXAMPLE),
+            setup_body(),
+            Body::read_to_end(&mut setup_buffer_reader(), BODY_EXAMPLE.len()).unwrap()
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "body is larger than 10 bytes")]
+    fn test_read_to_end_panic_if_larger_than_max_size() {
+        Body::read_to_end(&mut setup_buffer_reader(), 10).unwrap();
+    }
+
+    #[test]
+    fn test_read_chunked() {
+        let (body, trailers) = Body::read_chunked(
+            &mut BufReader::new("5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n".as_bytes()),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(setup_body(), body);
+        assert_eq!(Headers::new(), trailers);
+    }
+
+    #[test]
+    fn test_write_chunked() {
+        let mut buffer = Vec::new();
+        setup_body()
+            .write_chunked(&mut buffer, &Headers::new())
+            .unwrap();
+        assert_eq!(
+            format!("B\r\n{}\r\n0\r\n\r\n", BODY_EXAMPLE),
             String::from_utf8_lossy(&buffer).to_string()
         );
     }
 
     #[test]
     fn test_len() {
-        assert_eq!(11, setup_body().len())
+        assert_eq!(Some(11), setup_body().len())
+    }
+
+    #[test]
+    fn test_write_stream_with_length() {
+        let mut buffer = Vec::new();
+        Body::from_reader(BODY_EXAMPLE.as_bytes(), Some(5))
+            .write(&mut buffer)
+            .unwrap();
+        assert_eq!("hello", String::from_utf8_lossy(&buffer).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "body stream ended after 11 of 12 bytes")]
+    fn test_write_stream_panic_if_shorter_than_length() {
+        Body::from_reader(BODY_EXAMPLE.as_bytes(), Some(12))
+            .write(&mut Vec::new())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_write_stream_chunked() {
+        let mut buffer = Vec::new();
+        let mut body = Body::from_reader(BODY_EXAMPLE.as_bytes(), None);
+        assert_eq!(None, body.len());
+        body.write_chunked(&mut buffer, &Headers::new()).unwrap();
+        assert_eq!(
+            format!("B\r\n{}\r\n0\r\n\r\n", BODY_EXAMPLE),
+            String::from_utf8_lossy(&buffer).to_string()
+        );
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = std::env::temp_dir().join("webserver_test_body_from_file");
+        std::fs::write(&path, BODY_EXAMPLE).unwrap();
+        let mut body = Body::from_file(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Some(11), body.len());
+        let mut buffer = Vec::new();
+        body.write(&mut buffer).unwrap();
+        assert_eq!(BODY_EXAMPLE, String::from_utf8_lossy(&buffer).to_string());
     }
 
     #[test]
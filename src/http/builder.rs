@@ -0,0 +1,228 @@
+use super::headers::{is_valid_name, is_valid_value};
+use super::{Body, Error, Headers, Message, Method, Result, StartLine, Status, Version};
+
+/// Builder of a request or a response, created by `Message::request` or
+/// `Message::response`.
+///
+/// Errors are kept until `Builder::build` is called, so that calls can be
+/// chained.
+#[derive(Debug)]
+pub struct Builder {
+    start_line: Result<StartLine>,
+    headers: Headers,
+    body: Option<Body>,
+    error: Option<Error>,
+}
+
+impl Builder {
+    pub(super) fn new(start_line: Result<StartLine>) -> Self {
+        Self {
+            start_line,
+            headers: Headers::new(),
+            body: None,
+            error: None,
+        }
+    }
+
+    /// Add `value` to the header `name`, both being validated according to
+    /// RFC 9110. Adding the same header again sends it on several lines.
+    pub fn header(mut self, name: &str, value: impl AsRef<str>) -> Self {
+        let value = value.as_ref().trim_matches([' ', '\t']);
+        if !is_valid_name(name) {
+            self.error.get_or_insert(Error::MalformedHeaders(format!(
+                "invalid header name '{}'",
+                name
+            )));
+        } else if !is_valid_value(value) {
+            self.error.get_or_insert(Error::MalformedHeaders(format!(
+                "invalid value for header '{}'",
+                name
+            )));
+        } else {
+            self.headers.append(name, value);
+        }
+        self
+    }
+
+    /// Set the body, whose length is sent as `Content-Length` if known, or
+    /// which is sent chunked otherwise.
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    pub fn version(mut self, version: Version) -> Self {
+        if let Ok(
+            StartLine::Request {
+                version: current, ..
+            }
+            | StartLine::Response {
+                version: current, ..
+            },
+        ) = &mut self.start_line
+        {
+            *current = version;
+        }
+        self
+    }
+
+    /// Build the message, or return the first error met while building it.
+    ///
+    /// Informational, `204 No Content` and `304 Not Modified` responses can't
+    /// have a body.
+    pub fn build(self) -> Result<Message> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let start_line = self.start_line?;
+        if let StartLine::Response { status, .. } = &start_line {
+            if self.body.is_some() && !Message::may_have_body(&start_line) {
+                return Err(Error::MalformedBody(format!(
+                    "a {} response can't have a body",
+                    status
+                )));
+            }
+        }
+        Ok(Message::with_start_line(
+            start_line,
+            Some(self.headers),
+            self.body,
+        ))
+    }
+}
+
+impl Message {
+    /// Start building a request to `url`.
+    pub fn request(method: Method, url: impl AsRef<str>) -> Builder {
+        Builder::new(url.as_ref().parse().map(|url| StartLine::Request {
+            method,
+            url,
+            version: Version::V1_1,
+        }))
+    }
+
+    /// Start building a response.
+    pub fn response(status: Status) -> Builder {
+        Builder::new(Ok(StartLine::Response {
+            version: Version::V1_1,
+            status,
+            reason: status.reason().unwrap_or_default().to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_string(mut message: Message) -> String {
+        let mut buffer = Vec::new();
+        message.write(&mut buffer).unwrap();
+        String::from_utf8_lossy(&buffer).to_string()
+    }
+
+    #[test]
+    fn test_request() {
+        let request = Message::request(Method::Post, "/upload?name=a")
+            .header("Content-Type", "text/plain")
+            .body("hello")
+            .build()
+            .unwrap();
+        assert_eq!(
+            "POST /upload?name=a HTTP/1.1\r\nContent-Length: 5\r\nContent-Type: text/plain\r\n\r\nhello",
+            to_string(request)
+        );
+    }
+
+    #[test]
+    fn test_response() {
+        let response = Message::response(Status::NotFound)
+            .version(Version::V1_0)
+            .header("Server", " webserver ")
+            .build()
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\nServer: webserver\r\n\r\n",
+            to_string(response)
+        );
+    }
+
+    #[test]
+    fn test_response_with_repeated_header() {
+        let response = Message::response(Status::NoContent)
+            .header("Set-Cookie", "a=1")
+            .header("set-cookie", "b=2")
+            .build()
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.1 204 No Content\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\n\r\n",
+            to_string(response)
+        );
+    }
+
+    #[test]
+    fn test_response_with_stream_of_unknown_length() {
+        let response = Message::response(Status::Ok)
+            .body(Body::from_reader("hello".as_bytes(), None))
+            .build()
+            .unwrap();
+        assert!(response.headers().is_chunked());
+        assert_eq!(None, response.headers().get_content_length().unwrap());
+    }
+
+    #[test]
+    fn test_body_overrides_content_length() {
+        let response = Message::response(Status::Ok)
+            .header("Content-Length", "100")
+            .body("hello")
+            .build()
+            .unwrap();
+        assert_eq!(Some(5), response.headers().get_content_length().unwrap());
+    }
+
+    #[test]
+    fn test_body_overrides_transfer_encoding() {
+        let response = Message::response(Status::Ok)
+            .header("Transfer-Encoding", "chunked")
+            .body("hello")
+            .build()
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+            to_string(response)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "a 204 No Content response can't have a body")]
+    fn test_build_panic_if_body_is_not_allowed() {
+        Message::response(Status::NoContent)
+            .body("hello")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid header name 'Bad Name'")]
+    fn test_build_panic_if_invalid_header_name() {
+        Message::response(Status::Ok)
+            .header("Bad Name", "value")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for header 'X-Injected'")]
+    fn test_build_panic_if_invalid_header_value() {
+        Message::response(Status::Ok)
+            .header("X-Injected", "a\r\nSet-Cookie: b")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse url")]
+    fn test_build_panic_if_invalid_url() {
+        Message::request(Method::Get, "index.html").build().unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use super::headers::{is_valid_name, quote, split_quoted, unquote};
+use super::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The directives of a `Cache-Control` header, such as
+/// `max-age=3600, must-revalidate`.
+///
+/// Directive names are case-insensitive, so they are stored in lowercase.
+/// Directives unknown to this server are kept, so that they can be forwarded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl(Vec<(String, Option<String>)>);
+
+impl CacheControl {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Add the directive `name`, with an optional argument, replacing any
+    /// previous occurrence.
+    pub fn with(mut self, name: &str, argument: Option<&str>) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.0.retain(|(n, _)| *n != name);
+        self.0.push((name, argument.map(str::to_string)));
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+
+    /// Return the argument of the directive `name`, if it is present and has
+    /// one.
+    pub fn argument(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .and_then(|(_, argument)| argument.as_deref())
+    }
+
+    pub fn directives(&self) -> &[(String, Option<String>)] {
+        &self.0
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.seconds("max-age")
+    }
+
+    pub fn s_maxage(&self) -> Option<Duration> {
+        self.seconds("s-maxage")
+    }
+
+    pub fn no_cache(&self) -> bool {
+        self.contains("no-cache")
+    }
+
+    pub fn no_store(&self) -> bool {
+        self.contains("no-store")
+    }
+
+    /// Return the delta-seconds argument of the directive `name`, saturating
+    /// on overflow as allowed by RFC 9111.
+    fn seconds(&self, name: &str) -> Option<Duration> {
+        let seconds = self.argument(name)?;
+        if seconds.is_empty() || !seconds.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(Duration::from_secs(seconds.parse().unwrap_or(u64::MAX)))
+    }
+
+    /// Parse the directives held by the elements of a comma-separated list.
+    pub(super) fn from_elements<'a>(elements: impl Iterator<Item = &'a str>) -> Result<Self> {
+        let directives = elements
+            .map(|element| {
+                let malformed =
+                    || Error::MalformedHeaders(format!("invalid cache directive '{}'", element));
+                let (name, argument) = match element.split_once('=') {
+                    Some((name, argument)) if argument.starts_with('"') => {
+                        (name, Some(unquote(argument).ok_or_else(malformed)?))
+                    }
+                    Some((name, argument)) if is_valid_name(argument) => {
+                        (name, Some(argument.to_string()))
+                    }
+                    Some(_) => return Err(malformed()),
+                    None => (element, None),
+                };
+                if !is_valid_name(name) {
+                    return Err(malformed());
+                }
+                Ok((name.to_ascii_lowercase(), argument))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self(directives))
+    }
+}
+
+impl FromStr for CacheControl {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_elements(
+            split_quoted(s, ',')
+                .into_iter()
+                .map(|element| element.trim_matches([' ', '\t']))
+                .filter(|element| !element.is_empty()),
+        )
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, argument)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", name)?;
+            if let Some(argument) = argument {
+                write!(f, "={}", quote(argument))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let cache_control: CacheControl = "Max-Age=60, no-cache=\"Set-Cookie, Vary\", private"
+            .parse()
+            .unwrap();
+        assert_eq!(Some(Duration::from_secs(60)), cache_control.max_age());
+        assert_eq!(None, cache_control.s_maxage());
+        assert!(cache_control.no_cache());
+        assert!(!cache_control.no_store());
+        assert_eq!(Some("Set-Cookie, Vary"), cache_control.argument("no-cache"));
+        assert!(cache_control.contains("PRIVATE"));
+        assert_eq!(None, cache_control.argument("private"));
+    }
+
+    #[test]
+    fn test_display() {
+        let cache_control = CacheControl::new()
+            .with("public", None)
+            .with("max-age", Some("3600"))
+            .with("no-cache", Some("Set-Cookie, Vary"));
+        assert_eq!(
+            "public, max-age=3600, no-cache=\"Set-Cookie, Vary\"",
+            cache_control.to_string()
+        );
+        assert_eq!(cache_control, cache_control.to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn test_max_age_ignores_invalid_seconds() {
+        let cache_control: CacheControl = "max-age=-1".parse().unwrap();
+        assert_eq!(None, cache_control.max_age());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid cache directive 'max age=1'")]
+    fn test_from_str_panic_if_invalid_directive() {
+        "public, max age=1".parse::<CacheControl>().unwrap();
+    }
+}
@@ -0,0 +1,309 @@
+use super::message::{self, ParseOptions};
+use super::{Error, Headers, Result};
+use std::io::{self, BufRead, Read, Write};
+
+/// Maximum length of a line holding a chunk size and its extensions.
+const MAX_LINE_LENGTH: usize = 1024;
+
+/// Decode a chunked body from `bufread`, returning its content and the trailer
+/// fields that follow the last chunk.
+///
+/// The decoded body and the trailer section are bounded by the limits of
+/// `options`.
+pub fn read(bufread: &mut impl BufRead, options: &ParseOptions) -> Result<(Vec<u8>, Headers)> {
+    let mut body = Vec::new();
+    loop {
+        let size = read_chunk_size(bufread, options.strict)?;
+        if size == 0 {
+            break;
+        }
+        if size > options.max_body_size - body.len() {
+            return Err(Error::ContentTooLarge(format!(
+                "chunked body is larger than {} bytes",
+                options.max_body_size
+            )));
+        }
+        let start = body.len();
+        bufread.take(size as u64).read_to_end(&mut body)?;
+        if body.len() - start != size {
+            return Err(Error::MalformedBody(
+                "connection closed in the middle of a chunked body".to_string(),
+            ));
+        }
+        if !read_line(bufread, options.strict)?.is_empty() {
+            return Err(Error::MalformedBody(
+                "chunk data is not followed by CRLF".to_string(),
+            ));
+        }
+    }
+    let trailers = message::read_headers(bufread, options)?;
+    Ok((body, trailers))
+}
+
+/// Read a line as bytes, as chunk extensions and data aren't necessarily
+/// text.
+///
+/// A bare LF ends the line as well unless `strict` is set, in which case the
+/// line must end with a single CRLF.
+fn read_line(bufread: &mut impl BufRead, strict: bool) -> Result<Vec<u8>> {
+    let mut line = Vec::new();
+    bufread
+        .take(MAX_LINE_LENGTH as u64)
+        .read_until(b'\n', &mut line)?;
+    if !line.ends_with(b"\n") {
+        return Err(Error::MalformedBody(if line.len() == MAX_LINE_LENGTH {
+            format!("chunk line is longer than {} bytes", MAX_LINE_LENGTH)
+        } else {
+            "connection closed in the middle of a chunked body".to_string()
+        }));
+    }
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    } else if strict {
+        return Err(Error::MalformedBody(
+            "chunk line doesn't end with CRLF".to_string(),
+        ));
+    }
+    if strict && line.contains(&b'\r') {
+        return Err(Error::MalformedBody(
+            "chunk line holds a bare CR".to_string(),
+        ));
+    }
+    Ok(line)
+}
+
+fn read_chunk_size(bufread: &mut impl BufRead, strict: bool) -> Result<usize> {
+    let line = read_line(bufread, strict)?;
+    // Chunk extensions are allowed after a semicolon but none are supported
+    let size = line
+        .split(|b| *b == b';')
+        .next()
+        .unwrap_or_default()
+        .trim_ascii();
+    let size = String::from_utf8_lossy(size);
+    // from_str_radix would also accept a sign
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::MalformedBody(format!(
+            "couldn't parse chunk size '{}': expected hexadecimal digits",
+            size
+        )));
+    }
+    usize::from_str_radix(&size, 16)
+        .map_err(|e| Error::MalformedBody(format!("couldn't parse chunk size '{}': {}", size, e)))
+}
+
+/// Writer that frames every write as a chunk of a chunked body.
+///
+/// `ChunkedWriter::finish` must be called to write the last chunk and the
+/// trailer fields.
+pub struct ChunkedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Terminate the body with the last chunk and `trailers`, and return the
+    /// underlying writer.
+    pub fn finish(mut self, trailers: &Headers) -> Result<W> {
+        self.inner.write_all(b"0\r\n")?;
+        trailers.write(&mut self.inner)?;
+        self.inner.write_all(b"\r\n")?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // An empty chunk would be mistaken for the last one
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        write!(self.inner, "{:X}\r\n", buf.len())?;
+        self.inner.write_all(buf)?;
+        self.inner.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    const CHUNKED_EXAMPLE: &str = "\
+4\r
+Wiki\r
+6;name=value\r
+pedia \r
+E\r
+in \r
+\r
+chunks.\r
+0\r
+Expires: never\r
+\r
+next";
+
+    #[test]
+    fn test_read() {
+        let mut bufread = BufReader::new(CHUNKED_EXAMPLE.as_bytes());
+        let (body, trailers) = read(&mut bufread, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            "Wikipedia in \r\n\r\nchunks.",
+            String::from_utf8_lossy(&body)
+        );
+        assert_eq!(Some("never"), trailers.get("Expires"));
+        let mut rest = String::new();
+        bufread.read_line(&mut rest).unwrap();
+        assert_eq!("next", rest);
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse chunk size")]
+    fn test_read_panic_if_chunk_size_is_not_hexadecimal() {
+        read(
+            &mut BufReader::new("zz\r\nhello\r\n0\r\n\r\n".as_bytes()),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse chunk size '+5'")]
+    fn test_read_panic_if_chunk_size_is_signed() {
+        read(
+            &mut BufReader::new("+5\r\nhello\r\n0\r\n\r\n".as_bytes()),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "not followed by CRLF")]
+    fn test_read_panic_if_chunk_data_is_not_utf8() {
+        read(
+            &mut BufReader::new(&b"2\r\nhi\xff\xfe\r\n0\r\n\r\n"[..]),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse chunk size")]
+    fn test_read_panic_if_chunk_line_is_not_utf8() {
+        read(
+            &mut BufReader::new(&b"\xff5\r\nhello\r\n0\r\n\r\n"[..]),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_read_tolerates_bare_line_feeds() {
+        let (body, _) = read(
+            &mut BufReader::new("5\nhello\n0\n\n".as_bytes()),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(b"hello", &body[..]);
+    }
+
+    fn read_strict(s: &str) -> Result<(Vec<u8>, Headers)> {
+        read(
+            &mut BufReader::new(s.as_bytes()),
+            &ParseOptions {
+                strict: true,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk line doesn't end with CRLF")]
+    fn test_read_strict_panic_if_chunk_size_ends_with_bare_line_feed() {
+        read_strict("5\nhello\r\n0\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk line doesn't end with CRLF")]
+    fn test_read_strict_panic_if_chunk_data_ends_with_bare_line_feed() {
+        read_strict("5\r\nhello\n0\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk line holds a bare CR")]
+    fn test_read_strict_panic_if_chunk_line_ends_with_extra_carriage_return() {
+        read_strict("5\r\r\nhello\r\n0\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "not followed by CRLF")]
+    fn test_read_panic_if_chunk_is_longer_than_its_size() {
+        read(
+            &mut BufReader::new("2\r\nhello\r\n0\r\n\r\n".as_bytes()),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "connection closed")]
+    fn test_read_panic_if_last_chunk_is_missing() {
+        read(
+            &mut BufReader::new("5\r\nhello\r\n".as_bytes()),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_write() {
+        let mut writer = ChunkedWriter::new(Vec::new());
+        writer.write_all(b"Wiki").unwrap();
+        writer.write_all(b"").unwrap();
+        writer.write_all(b"pedia in chunks.").unwrap();
+        let buffer = writer
+            .finish(&Headers::from([(
+                String::from("Expires"),
+                String::from("never"),
+            )]))
+            .unwrap();
+        assert_eq!(
+            "4\r\nWiki\r\n10\r\npedia in chunks.\r\n0\r\nExpires: never\r\n\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk line is longer than 1024 bytes")]
+    fn test_read_panic_if_chunk_line_is_too_long() {
+        let body = format!("5;{}\r\nhello\r\n0\r\n\r\n", "x".repeat(MAX_LINE_LENGTH));
+        read(
+            &mut BufReader::new(body.as_bytes()),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 1 fields")]
+    fn test_read_panic_if_too_many_trailers() {
+        let options = ParseOptions {
+            max_headers: 1,
+            ..ParseOptions::default()
+        };
+        read(
+            &mut BufReader::new("0\r\nA: 1\r\nB: 2\r\n\r\n".as_bytes()),
+            &options,
+        )
+        .unwrap();
+    }
+}
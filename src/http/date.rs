@@ -0,0 +1,236 @@
+use super::{Error, Result};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const FULL_DAYS: [&str; 7] = [
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Convert a number of days since 1970-01-01 to a (year, month, day) civil
+/// date, month and day starting at 1.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Convert a (year, month, day) civil date to a number of days since
+/// 1970-01-01.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Format `time` as an HTTP-date, such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// Times before the Unix epoch are clamped to it.
+pub fn format(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0) as i64;
+    let (days, secs) = (secs / 86400, secs % 86400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Parse an HTTP-date in the preferred IMF-fixdate format, or in one of the
+/// obsolete RFC 850 and asctime formats which recipients must still accept.
+pub fn parse(s: &str) -> Result<SystemTime> {
+    let malformed = || Error::MalformedHeaders(format!("couldn't parse date '{}'", s));
+    let number = |n: &str, digits: std::ops::RangeInclusive<usize>| {
+        if !digits.contains(&n.len()) || !n.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(malformed());
+        }
+        n.parse::<u32>().map_err(|_| malformed())
+    };
+    let month = |m: &str| {
+        MONTHS
+            .iter()
+            .position(|name| *name == m)
+            .map(|i| i as u32 + 1)
+            .ok_or_else(malformed)
+    };
+    let s = s.trim();
+    let (day_name, year, month, day, time) = if let Some((day_name, rest)) = s.split_once(", ") {
+        let fields: Vec<&str> = rest.split(' ').collect();
+        match fields[..] {
+            // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+            [day, month_name, year, time, "GMT"] => (
+                day_name,
+                number(year, 4..=4)?,
+                month(month_name)?,
+                number(day, 2..=2)?,
+                time,
+            ),
+            // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+            [date, time, "GMT"] => {
+                let date: Vec<&str> = date.split('-').collect();
+                let [day, month_name, year] = date[..] else {
+                    return Err(malformed());
+                };
+                let day_name = FULL_DAYS
+                    .iter()
+                    .position(|d| *d == day_name)
+                    .ok_or_else(malformed)?;
+                (
+                    DAYS[day_name],
+                    two_digit_year(number(year, 2..=2)?),
+                    month(month_name)?,
+                    number(day, 2..=2)?,
+                    time,
+                )
+            }
+            _ => return Err(malformed()),
+        }
+    } else {
+        // asctime: Sun Nov  6 08:49:37 1994
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [day_name, month_name, day, time, year] = fields[..] else {
+            return Err(malformed());
+        };
+        (
+            day_name,
+            number(year, 4..=4)?,
+            month(month_name)?,
+            number(day, 1..=2)?,
+            time,
+        )
+    };
+    let time: Vec<&str> = time.split(':').collect();
+    let [hour, minute, second] = time[..] else {
+        return Err(malformed());
+    };
+    let (hour, minute, second) = (
+        number(hour, 2..=2)?,
+        number(minute, 2..=2)?,
+        number(second, 2..=2)?,
+    );
+    if !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return Err(malformed());
+    }
+    let days = days_from_civil(i64::from(year), month, day);
+    if days < 0 || DAYS[(days % 7) as usize] != day_name {
+        return Err(malformed());
+    }
+    let secs = days as u64 * 86400 + u64::from(hour * 3600 + minute * 60 + second);
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Expand a two-digit year to the most recent year ending with these digits
+/// that is not more than 50 years in the future, as required by RFC 9110.
+fn two_digit_year(year: u32) -> u32 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0) as i64;
+    let current = civil_from_days(secs / 86400).0 as u32;
+    let year = current - current % 100 + year;
+    if year > current + 50 {
+        year - 100
+    } else {
+        year
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATE_EXAMPLE: &str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+    fn setup_time() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(784111777)
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(DATE_EXAMPLE, format(setup_time()));
+        assert_eq!("Thu, 01 Jan 1970 00:00:00 GMT", format(UNIX_EPOCH));
+        assert_eq!(
+            "Thu, 29 Feb 2024 23:59:59 GMT",
+            format(UNIX_EPOCH + Duration::from_secs(1709251199))
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(setup_time(), parse(DATE_EXAMPLE).unwrap());
+        assert_eq!(
+            UNIX_EPOCH + Duration::from_secs(1709251199),
+            parse("Thu, 29 Feb 2024 23:59:59 GMT").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_obsolete_formats() {
+        assert_eq!(
+            setup_time(),
+            parse("Sunday, 06-Nov-94 08:49:37 GMT").unwrap()
+        );
+        assert_eq!(setup_time(), parse("Sun Nov  6 08:49:37 1994").unwrap());
+        assert_eq!(setup_time(), parse("Sun Nov 06 08:49:37 1994").unwrap());
+    }
+
+    #[test]
+    fn test_two_digit_year() {
+        assert_eq!(1994, two_digit_year(94));
+        assert_eq!(2024, two_digit_year(24));
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse date")]
+    fn test_parse_panic_if_abbreviated_day_name_in_rfc_850_date() {
+        parse("Sun, 06-Nov-94 08:49:37 GMT").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse date")]
+    fn test_parse_panic_if_day_name_does_not_match() {
+        parse("Mon, 06 Nov 1994 08:49:37 GMT").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse date")]
+    fn test_parse_panic_if_not_gmt() {
+        parse("Sun, 06 Nov 1994 08:49:37 CET").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse date")]
+    fn test_parse_panic_if_malformed_time() {
+        parse("Sun, 06 Nov 1994 8:49:37 GMT").unwrap();
+    }
+}
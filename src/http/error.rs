@@ -1,3 +1,4 @@
+use super::{Method, Status};
 use std::{error, fmt, io};
 
 #[derive(Debug)]
@@ -6,16 +7,57 @@ pub enum Error {
     Io(io::Error),
     /// Request line is malformed
     MalformedRequestLine(String),
+    /// Status line is malformed
+    MalformedStatusLine(String),
     /// Headers are malformed
     MalformedHeaders(String),
+    /// Body is malformed
+    MalformedBody(String),
+    /// Start line is longer than allowed
+    UriTooLong(String),
+    /// Header or trailer section is larger than allowed
+    HeaderFieldsTooLarge(String),
+    /// Body is larger than allowed
+    ContentTooLarge(String),
+    /// Request wasn't received in time
+    RequestTimeout(String),
     /// Rendering of index page failed
     IndexRendering(ramhorns::Error),
-    IndexGeneration(String)
-    
+    IndexGeneration(String),
+    /// Request method is not supported by the resource, which allows the
+    /// given methods instead
+    MethodNotAllowed(Vec<Method>),
+    /// Request method is well-formed but unknown to the server
+    NotImplemented(String),
+    /// Request sent by the client couldn't be completed
+    Client(String),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+impl Error {
+    /// Return the status of the response answering a request that failed with
+    /// this error.
+    pub fn status(&self) -> Status {
+        match self {
+            Error::MalformedRequestLine(_)
+            | Error::MalformedStatusLine(_)
+            | Error::MalformedHeaders(_)
+            | Error::MalformedBody(_) => Status::BadRequest,
+            Error::UriTooLong(_) => Status::UriTooLong,
+            Error::HeaderFieldsTooLarge(_) => Status::RequestHeaderFieldsTooLarge,
+            Error::ContentTooLarge(_) => Status::ContentTooLarge,
+            Error::RequestTimeout(_) => Status::RequestTimeout,
+            Error::MethodNotAllowed(_) => Status::MethodNotAllowed,
+            Error::NotImplemented(_) => Status::NotImplemented,
+            Error::Io(_)
+            | Error::IndexRendering(_)
+            | Error::IndexGeneration(_)
+            | Error::Client(_) => Status::InternalServerError,
+        }
+    }
+}
+
 impl error::Error for Error {}
 
 impl fmt::Display for Error {
@@ -23,9 +65,26 @@ impl fmt::Display for Error {
         match self {
             Error::Io(e) => write!(f, "{}", e),
             Error::MalformedRequestLine(r) => write!(f, "malformed request line {}", r),
+            Error::MalformedStatusLine(r) => write!(f, "malformed status line {}", r),
             Error::MalformedHeaders(r) => write!(f, "malformed headers {}", r),
+            Error::MalformedBody(r) => write!(f, "malformed body {}", r),
+            Error::UriTooLong(r) => write!(f, "request line too long {}", r),
+            Error::HeaderFieldsTooLarge(r) => write!(f, "header fields too large {}", r),
+            Error::ContentTooLarge(r) => write!(f, "content too large {}", r),
+            Error::RequestTimeout(r) => write!(f, "request timeout {}", r),
             Error::IndexRendering(e) => write!(f, "failed rendering index {}", e),
             Error::IndexGeneration(s) => write!(f, "failed generating index {}", s),
+            Error::Client(r) => write!(f, "client request failed {}", r),
+            Error::NotImplemented(r) => write!(f, "not implemented {}", r),
+            Error::MethodNotAllowed(allowed) => write!(
+                f,
+                "method not allowed, expected one of {}",
+                allowed
+                    .iter()
+                    .map(Method::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
@@ -41,3 +100,40 @@ impl From<ramhorns::Error> for Error {
         Error::IndexRendering(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        assert_eq!(
+            Status::BadRequest,
+            Error::MalformedHeaders(String::new()).status()
+        );
+        assert_eq!(
+            Status::RequestHeaderFieldsTooLarge,
+            Error::HeaderFieldsTooLarge(String::new()).status()
+        );
+        assert_eq!(
+            Status::MethodNotAllowed,
+            Error::MethodNotAllowed(vec![Method::Get]).status()
+        );
+        assert_eq!(
+            Status::NotImplemented,
+            Error::NotImplemented(String::new()).status()
+        );
+        assert_eq!(
+            Status::InternalServerError,
+            Error::Io(io::Error::from(io::ErrorKind::PermissionDenied)).status()
+        );
+    }
+
+    #[test]
+    fn test_to_string_method_not_allowed() {
+        assert_eq!(
+            "method not allowed, expected one of GET, HEAD",
+            Error::MethodNotAllowed(vec![Method::Get, Method::Head]).to_string()
+        );
+    }
+}
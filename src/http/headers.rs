@@ -1,9 +1,54 @@
-use super::{Error, Result};
+use super::quality::{self, QualityItem};
+use super::{date, CacheControl, Error, MediaType, Method, Result, Url};
 use std::collections::BTreeMap;
+use std::fmt;
 use std::io::Write;
+use std::time::SystemTime;
 
-#[derive(Debug, PartialEq)]
-pub struct Headers(BTreeMap<String, String>);
+/// Return whether `name` is a valid field name, made of token characters.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|b| {
+            b.is_ascii_alphanumeric()
+                || matches!(
+                    b,
+                    b'!' | b'#'
+                        | b'$'
+                        | b'%'
+                        | b'&'
+                        | b'\''
+                        | b'*'
+                        | b'+'
+                        | b'-'
+                        | b'.'
+                        | b'^'
+                        | b'_'
+                        | b'`'
+                        | b'|'
+                        | b'~'
+                )
+        })
+}
+
+/// Return whether `value` is a valid field value, which excludes control
+/// characters such as CR and LF but horizontal tabs.
+pub fn is_valid_value(value: &str) -> bool {
+    value.bytes().all(|b| b == b'\t' || !b.is_ascii_control())
+}
+
+/// Header fields, looked up case-insensitively.
+///
+/// Fields are ordered by name, and the values of a field repeated over several
+/// lines are kept in order. Each field keeps the spelling of its name when
+/// first added.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Headers(BTreeMap<String, Field>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    name: String,
+    values: Vec<String>,
+}
 
 impl Headers {
     pub fn new() -> Self {
@@ -11,52 +56,456 @@ impl Headers {
     }
 
     pub fn read(head: impl Iterator<Item = String>) -> Result<Self> {
-        match head
-            .take_while(|s| !s.is_empty())
-            .map(|s| {
-                let (k, v) = s.split_at(s.find(":")?);
-                Some((
-                    String::from(k),
-                    String::from(if v.len() > 1 { &v[2..] } else { "" }),
-                ))
-            })
-            .collect()
-        {
-            Some(header) => Ok(Headers(header)),
-            None => Err(Error::MalformedHeaders(
+        Self::parse(head, false)
+    }
+
+    /// Read headers following RFC 9112 to the letter, rejecting invalid field
+    /// names and values, whitespace before the colon and obsolete line
+    /// folding, which are otherwise tolerated.
+    pub fn read_strict(head: impl Iterator<Item = String>) -> Result<Self> {
+        Self::parse(head, true)
+    }
+
+    fn parse(head: impl Iterator<Item = String>, strict: bool) -> Result<Self> {
+        let mut headers = Headers::new();
+        let mut last = None;
+        for line in head.take_while(|s| !s.is_empty()) {
+            if line.starts_with([' ', '\t']) {
+                // A line folded onto the previous one continues its value
+                let value = last
+                    .as_ref()
+                    .filter(|_| !strict)
+                    .and_then(|key| headers.0.get_mut(key))
+                    .and_then(|field| field.values.last_mut())
+                    .ok_or(Error::MalformedHeaders(
+                        "obsolete line folding isn't allowed".to_string(),
+                    ))?;
+                value.push(' ');
+                value.push_str(line.trim_matches([' ', '\t']));
+                continue;
+            }
+            let (name, value) = line.split_once(':').ok_or(Error::MalformedHeaders(
                 "couldn't parse headers".to_string(),
-            )),
+            ))?;
+            if strict && !is_valid_name(name) {
+                return Err(Error::MalformedHeaders(format!(
+                    "invalid header name '{}'",
+                    name
+                )));
+            }
+            let value = value.trim_matches([' ', '\t']);
+            if strict && !is_valid_value(value) {
+                return Err(Error::MalformedHeaders(format!(
+                    "invalid value for header '{}'",
+                    name
+                )));
+            }
+            let name = name.trim_end_matches([' ', '\t']);
+            headers.append(name, value);
+            last = Some(name.to_ascii_lowercase());
         }
+        Ok(headers)
     }
 
     pub fn write(&self, bufwrite: &mut impl Write) -> Result<()> {
-        for (k, v) in &self.0 {
+        for (k, v) in self.iter() {
             bufwrite.write_fmt(format_args!("{}: {}\r\n", k, v))?;
         }
         Ok(())
     }
 
-    pub fn get_content_length(&self) -> Option<usize> {
+    /// Iterate over every (name, value) pair, a repeated field giving one pair
+    /// per value.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.values().flat_map(|field| {
+            field
+                .values
+                .iter()
+                .map(move |value| (field.name.as_str(), value.as_str()))
+        })
+    }
+
+    /// Return the first value of the field `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).into_iter().next()
+    }
+
+    /// Return every value of the field `name`, in the order they were added.
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
         self.0
-            .get("Content-Length")
-            .map(|size| size.parse().unwrap_or(0))
+            .get(&name.to_ascii_lowercase())
+            .map(|field| field.values.iter().map(String::as_str).collect())
+            .unwrap_or_default()
     }
 
-    pub fn set_content_length(&mut self, size: usize) {
+    /// Return the elements of the comma-separated list held by every value of
+    /// the field `name`, leaving aside empty elements and commas within
+    /// quoted strings.
+    pub fn get_list(&self, name: &str) -> Vec<&str> {
+        self.get_all(name)
+            .into_iter()
+            .flat_map(split_list)
+            .collect()
+    }
+
+    /// Set the field `name` to `value`, replacing any previous value.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.remove(&name);
+        self.append(name, value);
+    }
+
+    /// Add `value` to the field `name`, after any previous value.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
         self.0
-            .insert(String::from("Content-Length"), size.to_string());
+            .entry(name.to_ascii_lowercase())
+            .or_insert_with(|| Field {
+                name,
+                values: Vec::new(),
+            })
+            .values
+            .push(value.into());
+    }
+
+    /// Remove the field `name`, returning its values.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        self.0
+            .remove(&name.to_ascii_lowercase())
+            .map(|field| field.values)
+            .unwrap_or_default()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Return whether the `Connection` header lists the given option, such as
+    /// `close` or `keep-alive`.
+    pub fn has_connection_option(&self, option: &str) -> bool {
+        self.get_list("Connection")
+            .iter()
+            .any(|token| token.eq_ignore_ascii_case(option))
+    }
+
+    /// Return whether the body is sent with the chunked transfer coding, which
+    /// must then be the last coding applied.
+    pub fn is_chunked(&self) -> bool {
+        self.get_list("Transfer-Encoding")
+            .last()
+            .is_some_and(|coding| coding.eq_ignore_ascii_case("chunked"))
+    }
+
+    /// Mark the body as sent with the chunked transfer coding, which replaces
+    /// any `Content-Length`.
+    pub fn set_chunked(&mut self) {
+        self.remove("Content-Length");
+        self.set("Transfer-Encoding", "chunked");
+    }
+
+    /// Return the length of the `Content-Length` header, which must only hold
+    /// digits, or else the body couldn't be delimited. A length that doesn't
+    /// fit in a `usize` is too large to be read.
+    ///
+    /// The header may be repeated, or hold a list, of the same length only.
+    pub fn get_content_length(&self) -> Result<Option<usize>> {
+        let sizes = self.get_list("Content-Length");
+        let Some(size) = sizes.first().copied() else {
+            return Ok(None);
+        };
+        if sizes.iter().any(|other| *other != size) {
+            return Err(Error::MalformedHeaders(String::from(
+                "conflicting Content-Length values",
+            )));
+        }
+        if size.is_empty() || !size.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::MalformedHeaders(format!(
+                "invalid Content-Length '{}'",
+                size
+            )));
+        }
+        size.parse().map(Some).map_err(|_| {
+            Error::ContentTooLarge(format!(
+                "body of {} bytes is larger than {} bytes",
+                size,
+                usize::MAX
+            ))
+        })
+    }
+
+    pub fn set_content_length(&mut self, size: usize) {
+        self.set("Content-Length", size.to_string());
+    }
+
+    pub fn get_content_type(&self) -> Result<Option<MediaType>> {
+        self.get("Content-Type").map(str::parse).transpose()
+    }
+
+    pub fn set_content_type(&mut self, media_type: &MediaType) {
+        self.set("Content-Type", media_type.to_string());
+    }
+
+    /// Return the host and optional port of the `Host` header, an IPv6 host
+    /// keeping its brackets.
+    pub fn get_host(&self) -> Result<Option<(&str, Option<u16>)>> {
+        let Some(value) = self.get("Host") else {
+            return Ok(None);
+        };
+        let malformed = || Error::MalformedHeaders(format!("invalid Host header '{}'", value));
+        let end = if value.starts_with('[') {
+            value.find(']').ok_or_else(malformed)? + 1
+        } else {
+            value.find(':').unwrap_or(value.len())
+        };
+        let (host, port) = value.split_at(end);
+        let valid = match host.strip_prefix('[') {
+            Some(ip) => ip.strip_suffix(']').is_some_and(|ip| {
+                ip.bytes()
+                    .all(|b| b.is_ascii_hexdigit() || b == b':' || b == b'.')
+            }),
+            None => host
+                .bytes()
+                .all(|b| b.is_ascii_graphic() && !b"/?#@[]".contains(&b)),
+        };
+        if !valid {
+            return Err(malformed());
+        }
+        let port = match port {
+            "" | ":" => None,
+            _ => Some(
+                port.strip_prefix(':')
+                    .filter(|port| port.bytes().all(|b| b.is_ascii_digit()))
+                    .and_then(|port| port.parse().ok())
+                    .ok_or_else(malformed)?,
+            ),
+        };
+        Ok(Some((host, port)))
+    }
+
+    pub fn set_host(&mut self, host: &str, port: Option<u16>) {
+        match port {
+            Some(port) => self.set("Host", format!("{}:{}", host, port)),
+            None => self.set("Host", host),
+        }
+    }
+
+    /// Return the options of the `Connection` header, in lowercase.
+    pub fn get_connection(&self) -> Result<Vec<String>> {
+        self.get_list("Connection")
+            .into_iter()
+            .map(quality::parse_token)
+            .collect()
+    }
+
+    pub fn set_connection(&mut self, options: &[&str]) {
+        self.set("Connection", options.join(", "));
+    }
+
+    /// Return the transfer codings of the `Transfer-Encoding` header in the
+    /// order they were applied, in lowercase and without their parameters.
+    pub fn get_transfer_encoding(&self) -> Result<Vec<String>> {
+        self.get_list("Transfer-Encoding")
+            .into_iter()
+            .map(|coding| quality::parse_token(split_quoted(coding, ';')[0]))
+            .collect()
+    }
+
+    pub fn set_transfer_encoding(&mut self, codings: &[&str]) {
+        self.remove("Content-Length");
+        self.set("Transfer-Encoding", codings.join(", "));
+    }
+
+    /// Return the media ranges of the `Accept` header, by decreasing quality.
+    pub fn get_accept(&self) -> Result<Vec<QualityItem<MediaType>>> {
+        quality::parse_list(self.get_list("Accept").into_iter(), str::parse)
+    }
+
+    pub fn set_accept(&mut self, media_ranges: &[QualityItem<MediaType>]) {
+        self.set("Accept", join(media_ranges));
+    }
+
+    /// Return the charsets of the `Accept-Charset` header, by decreasing
+    /// quality.
+    pub fn get_accept_charset(&self) -> Result<Vec<QualityItem<String>>> {
+        self.get_quality_tokens("Accept-Charset")
+    }
+
+    pub fn set_accept_charset(&mut self, charsets: &[QualityItem<String>]) {
+        self.set("Accept-Charset", join(charsets));
     }
+
+    /// Return the content codings of the `Accept-Encoding` header, by
+    /// decreasing quality.
+    pub fn get_accept_encoding(&self) -> Result<Vec<QualityItem<String>>> {
+        self.get_quality_tokens("Accept-Encoding")
+    }
+
+    pub fn set_accept_encoding(&mut self, codings: &[QualityItem<String>]) {
+        self.set("Accept-Encoding", join(codings));
+    }
+
+    /// Return the language ranges of the `Accept-Language` header, by
+    /// decreasing quality.
+    pub fn get_accept_language(&self) -> Result<Vec<QualityItem<String>>> {
+        self.get_quality_tokens("Accept-Language")
+    }
+
+    pub fn set_accept_language(&mut self, languages: &[QualityItem<String>]) {
+        self.set("Accept-Language", join(languages));
+    }
+
+    fn get_quality_tokens(&self, name: &str) -> Result<Vec<QualityItem<String>>> {
+        quality::parse_list(self.get_list(name).into_iter(), quality::parse_token)
+    }
+
+    pub fn get_date(&self) -> Result<Option<SystemTime>> {
+        self.get("Date").map(date::parse).transpose()
+    }
+
+    pub fn set_date(&mut self, time: SystemTime) {
+        self.set("Date", date::format(time));
+    }
+
+    pub fn get_last_modified(&self) -> Result<Option<SystemTime>> {
+        self.get("Last-Modified").map(date::parse).transpose()
+    }
+
+    pub fn set_last_modified(&mut self, time: SystemTime) {
+        self.set("Last-Modified", date::format(time));
+    }
+
+    /// Return the directives of every `Cache-Control` header.
+    pub fn get_cache_control(&self) -> Result<Option<CacheControl>> {
+        if !self.contains("Cache-Control") {
+            return Ok(None);
+        }
+        CacheControl::from_elements(self.get_list("Cache-Control").into_iter()).map(Some)
+    }
+
+    pub fn set_cache_control(&mut self, cache_control: &CacheControl) {
+        self.set("Cache-Control", cache_control.to_string());
+    }
+
+    pub fn get_location(&self) -> Result<Option<Url>> {
+        self.get("Location")
+            .map(|location| {
+                location.parse().map_err(|_| {
+                    Error::MalformedHeaders(format!("invalid Location header '{}'", location))
+                })
+            })
+            .transpose()
+    }
+
+    pub fn set_location(&mut self, url: &Url) {
+        self.set("Location", url.to_string());
+    }
+
+    /// Return the methods of the `Allow` header, leaving aside the ones
+    /// unknown to this server.
+    pub fn get_allow(&self) -> Result<Vec<Method>> {
+        let mut methods = Vec::new();
+        for method in self.get_list("Allow") {
+            if !is_valid_name(method) {
+                return Err(Error::MalformedHeaders(format!(
+                    "invalid method '{}' in Allow header",
+                    method
+                )));
+            }
+            methods.extend(method.parse::<Method>().ok());
+        }
+        Ok(methods)
+    }
+
+    pub fn set_allow(&mut self, methods: &[Method]) {
+        self.set("Allow", join(methods));
+    }
+}
+
+/// Split `value` at each `separator` found outside of quoted strings.
+pub(super) fn split_quoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Split a comma-separated list, trimming its elements and leaving aside empty
+/// ones.
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    split_quoted(value, ',')
+        .into_iter()
+        .map(|element| element.trim_matches([' ', '\t']))
+        .filter(|element| !element.is_empty())
+}
+
+/// Return the content of the quoted string `s`, with its escapes removed, or
+/// `None` if `s` isn't exactly one quoted string.
+pub(super) fn unquote(s: &str) -> Option<String> {
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut content = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => content.push(chars.next()?),
+            '"' => return None,
+            c => content.push(c),
+        }
+    }
+    Some(content)
+}
+
+/// Return `s` as is if it is a token, or as a quoted string otherwise.
+pub(super) fn quote(s: &str) -> String {
+    if is_valid_name(s) {
+        return s.to_string();
+    }
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Join `items` as a comma-separated list.
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(T::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl<const N: usize> From<[(String, String); N]> for Headers {
     fn from(map: [(String, String); N]) -> Self {
-        Self(BTreeMap::from_iter(map))
+        let mut headers = Headers::new();
+        for (k, v) in map {
+            headers.append(k, v);
+        }
+        headers
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
 
     const HEADER_EXAMPLE: &str = "\
 Content-Type: text/plain
@@ -79,6 +528,64 @@ User-Agent: curl";
         );
     }
 
+    #[test]
+    fn test_read_trims_values() {
+        let headers = Headers::read(
+            [
+                "Host:example.com",
+                "Accept: \t*/* ",
+                "Empty:",
+                "Name : value",
+            ]
+            .into_iter()
+            .map(String::from),
+        )
+        .unwrap();
+        assert_eq!(Some("example.com"), headers.get("Host"));
+        assert_eq!(Some("*/*"), headers.get("Accept"));
+        assert_eq!(Some(""), headers.get("Empty"));
+        assert_eq!(Some("value"), headers.get("Name"));
+    }
+
+    #[test]
+    fn test_read_unfolds_lines() {
+        let headers =
+            Headers::read(["X-Long: a,", " b,", "\tc"].into_iter().map(String::from)).unwrap();
+        assert_eq!(Some("a, b, c"), headers.get("X-Long"));
+    }
+
+    #[test]
+    fn test_read_strict() {
+        assert_eq!(
+            setup_header(),
+            Headers::read_strict(HEADER_EXAMPLE.lines().map(|s| s.to_string())).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "obsolete line folding isn't allowed")]
+    fn test_read_strict_panic_if_folded_line() {
+        Headers::read_strict(["X-Long: a,", " b"].into_iter().map(String::from)).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid header name 'Host '")]
+    fn test_read_strict_panic_if_whitespace_before_colon() {
+        Headers::read_strict(["Host : example.com"].into_iter().map(String::from)).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid header name 'X(Y)'")]
+    fn test_read_strict_panic_if_invalid_name() {
+        Headers::read_strict(["X(Y): z"].into_iter().map(String::from)).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value for header 'X'")]
+    fn test_read_strict_panic_if_invalid_value() {
+        Headers::read_strict(["X: a\rb"].into_iter().map(String::from)).unwrap();
+    }
+
     #[test]
     fn test_write() {
         let headers = setup_header();
@@ -90,15 +597,363 @@ User-Agent: curl";
         );
     }
 
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("Content-Type"));
+        assert!(is_valid_name("X-Custom_Header.1"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("Content Type"));
+        assert!(!is_valid_name("Content-Type:"));
+    }
+
+    #[test]
+    fn test_is_valid_value() {
+        assert!(is_valid_value("text/html; charset=utf-8"));
+        assert!(is_valid_value("a\tb café"));
+        assert!(!is_valid_value("a\r\nb"));
+        assert!(!is_valid_value("a\0b"));
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let mut headers = setup_header();
+        assert_eq!(Some("curl"), headers.get("User-Agent"));
+        assert_eq!(None, headers.get("Host"));
+        headers.set("Host", "localhost");
+        assert_eq!(Some("localhost"), headers.get("Host"));
+    }
+
+    #[test]
+    fn test_has_connection_option() {
+        let mut headers = setup_header();
+        assert!(!headers.has_connection_option("close"));
+        headers.set("Connection", "Upgrade");
+        headers.append("connection", "Close");
+        assert!(headers.has_connection_option("close"));
+        assert!(headers.has_connection_option("upgrade"));
+        assert!(!headers.has_connection_option("keep-alive"));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut headers = setup_header();
+        assert_eq!(vec![String::from("curl")], headers.remove("user-agent"));
+        assert!(headers.remove("User-Agent").is_empty());
+        assert!(!headers.contains("User-Agent"));
+    }
+
+    #[test]
+    fn test_get_is_case_insensitive() {
+        let headers = Headers::read(
+            "content-length: 5\nX-CUSTOM: a"
+                .lines()
+                .map(|s| s.to_string()),
+        )
+        .unwrap();
+        assert_eq!(Some(5), headers.get_content_length().unwrap());
+        assert_eq!(Some("a"), headers.get("x-custom"));
+        assert!(headers.contains("X-Custom"));
+    }
+
+    #[test]
+    fn test_write_preserves_casing() {
+        let mut headers = Headers::new();
+        headers.append("x-lower", "1");
+        headers.append("X-LOWER", "2");
+        headers.set("ETag", "\"a\"");
+        let mut buffer = Vec::new();
+        headers.write(&mut buffer).unwrap();
+        assert_eq!(
+            "ETag: \"a\"\r\nx-lower: 1\r\nx-lower: 2\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
+    #[test]
+    fn test_read_repeated_fields() {
+        let headers = Headers::read(
+            "Set-Cookie: a=1\nAccept: text/html\nSet-Cookie: b=2\nAccept: text/plain"
+                .lines()
+                .map(|s| s.to_string()),
+        )
+        .unwrap();
+        assert_eq!(vec!["a=1", "b=2"], headers.get_all("Set-Cookie"));
+        assert_eq!(Some("a=1"), headers.get("set-cookie"));
+        assert_eq!(vec!["text/html", "text/plain"], headers.get_list("Accept"));
+        assert!(headers.get_all("Host").is_empty());
+    }
+
+    #[test]
+    fn test_append_and_set() {
+        let mut headers = Headers::new();
+        headers.append("Vary", "Accept");
+        headers.append("vary", "Range");
+        assert_eq!(vec!["Accept", "Range"], headers.get_all("Vary"));
+        headers.set("VARY", "*");
+        assert_eq!(vec!["*"], headers.get_all("Vary"));
+    }
+
+    #[test]
+    fn test_get_list() {
+        let mut headers = Headers::new();
+        headers.append("If-None-Match", "\"a,b\", , W/\"c\\\"d\"");
+        headers.append("If-None-Match", "\"e\"");
+        assert_eq!(
+            vec!["\"a,b\"", "W/\"c\\\"d\"", "\"e\""],
+            headers.get_list("If-None-Match")
+        );
+    }
+
+    #[test]
+    fn test_is_chunked() {
+        let mut headers = setup_header();
+        assert!(!headers.is_chunked());
+        headers.set("Transfer-Encoding", "gzip, Chunked");
+        assert!(headers.is_chunked());
+        headers.set("Transfer-Encoding", "chunked, gzip");
+        assert!(!headers.is_chunked());
+    }
+
+    #[test]
+    fn test_set_chunked() {
+        let mut headers = setup_header();
+        headers.set_chunked();
+        assert!(headers.is_chunked());
+        assert_eq!(None, headers.get_content_length().unwrap());
+    }
+
     #[test]
     fn test_get_content_length() {
-        assert_eq!(Some(50), setup_header().get_content_length());
+        assert_eq!(Some(50), setup_header().get_content_length().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "body of 99999999999999999999999 bytes is larger than")]
+    fn test_get_content_length_panic_if_overflowing() {
+        let mut headers = Headers::new();
+        headers.set("Content-Length", "99999999999999999999999");
+        headers.get_content_length().unwrap();
+    }
+
+    #[test]
+    fn test_get_content_length_repeated() {
+        let mut headers = Headers::new();
+        headers.append("Content-Length", "5, 5");
+        headers.append("Content-Length", "5");
+        assert_eq!(Some(5), headers.get_content_length().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting Content-Length values")]
+    fn test_get_content_length_panic_if_conflicting() {
+        let mut headers = Headers::new();
+        headers.append("Content-Length", "5");
+        headers.append("Content-Length", "6");
+        headers.get_content_length().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Content-Length 'five'")]
+    fn test_get_content_length_panic_if_not_numeric() {
+        let mut headers = Headers::new();
+        headers.set("Content-Length", "five");
+        headers.get_content_length().unwrap();
     }
 
     #[test]
     fn test_set_content_length() {
         let mut headers = setup_header();
         headers.set_content_length(40);
-        assert_eq!(Some(40), headers.get_content_length());
+        assert_eq!(Some(40), headers.get_content_length().unwrap());
+    }
+
+    #[test]
+    fn test_get_and_set_content_type() {
+        let mut headers = setup_header();
+        assert_eq!(
+            Some(MediaType::new("text", "plain")),
+            headers.get_content_type().unwrap()
+        );
+        headers
+            .set_content_type(&MediaType::new("text", "html").with_parameter("charset", "utf-8"));
+        assert_eq!(
+            Some("text/html; charset=utf-8"),
+            headers.get("Content-Type")
+        );
+        assert_eq!(None, Headers::new().get_content_type().unwrap());
+    }
+
+    #[test]
+    fn test_get_and_set_host() {
+        let mut headers = Headers::new();
+        assert_eq!(None, headers.get_host().unwrap());
+        headers.set_host("example.com", None);
+        assert_eq!(Some(("example.com", None)), headers.get_host().unwrap());
+        headers.set_host("[::1]", Some(8080));
+        assert_eq!(Some(("[::1]", Some(8080))), headers.get_host().unwrap());
+        headers.set("Host", "");
+        assert_eq!(Some(("", None)), headers.get_host().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Host header 'example.com:http'")]
+    fn test_get_host_panic_if_invalid_port() {
+        let mut headers = Headers::new();
+        headers.set("Host", "example.com:http");
+        headers.get_host().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Host header 'user@example.com'")]
+    fn test_get_host_panic_if_invalid_host() {
+        let mut headers = Headers::new();
+        headers.set("Host", "user@example.com");
+        headers.get_host().unwrap();
+    }
+
+    #[test]
+    fn test_get_and_set_connection() {
+        let mut headers = Headers::new();
+        headers.set_connection(&["Keep-Alive", "Upgrade"]);
+        assert_eq!(
+            vec!["keep-alive", "upgrade"],
+            headers.get_connection().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_and_set_transfer_encoding() {
+        let mut headers = setup_header();
+        headers.set_transfer_encoding(&["gzip", "chunked"]);
+        assert_eq!(None, headers.get_content_length().unwrap());
+        assert!(headers.is_chunked());
+        headers.set("Transfer-Encoding", "GZIP;level=1, chunked");
+        assert_eq!(
+            vec!["gzip", "chunked"],
+            headers.get_transfer_encoding().unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid token")]
+    fn test_get_transfer_encoding_panic_if_invalid_coding() {
+        let mut headers = Headers::new();
+        headers.set("Transfer-Encoding", "chunked, \"gzip\"");
+        headers.get_transfer_encoding().unwrap();
+    }
+
+    #[test]
+    fn test_get_and_set_accept() {
+        let mut headers = Headers::new();
+        headers.append("Accept", "text/*;q=0.5, application/json");
+        headers.append("Accept", "*/*;q=0.1");
+        let accept = headers.get_accept().unwrap();
+        assert_eq!(
+            vec![
+                QualityItem::new(MediaType::new("application", "json"), 1000),
+                QualityItem::new(MediaType::new("text", "*"), 500),
+                QualityItem::new(MediaType::new("*", "*"), 100),
+            ],
+            accept
+        );
+        headers.set_accept(&accept);
+        assert_eq!(
+            Some("application/json, text/*;q=0.5, */*;q=0.1"),
+            headers.get("Accept")
+        );
+    }
+
+    #[test]
+    fn test_get_and_set_accept_tokens() {
+        let mut headers = Headers::new();
+        headers.set("Accept-Encoding", "gzip;q=0.8, BR");
+        headers.set("Accept-Language", "fr-CH, fr;q=0.9, *;q=0.5");
+        assert_eq!(
+            vec![
+                QualityItem::new(String::from("br"), 1000),
+                QualityItem::new(String::from("gzip"), 800),
+            ],
+            headers.get_accept_encoding().unwrap()
+        );
+        assert_eq!(3, headers.get_accept_language().unwrap().len());
+        assert!(headers.get_accept_charset().unwrap().is_empty());
+        headers.set_accept_charset(&[QualityItem::new(String::from("utf-8"), 0)]);
+        assert_eq!(Some("utf-8;q=0"), headers.get("Accept-Charset"));
+    }
+
+    #[test]
+    fn test_get_and_set_dates() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+        let mut headers = Headers::new();
+        assert_eq!(None, headers.get_date().unwrap());
+        headers.set_date(time);
+        assert_eq!(Some("Sun, 06 Nov 1994 08:49:37 GMT"), headers.get("Date"));
+        headers.set("Last-Modified", "Sunday, 06-Nov-94 08:49:37 GMT");
+        assert_eq!(Some(time), headers.get_last_modified().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse date")]
+    fn test_get_date_panic_if_malformed() {
+        let mut headers = Headers::new();
+        headers.set("Date", "yesterday");
+        headers.get_date().unwrap();
+    }
+
+    #[test]
+    fn test_get_and_set_cache_control() {
+        let mut headers = Headers::new();
+        assert_eq!(None, headers.get_cache_control().unwrap());
+        headers.append("Cache-Control", "no-cache");
+        headers.append("Cache-Control", "max-age=60");
+        let cache_control = headers.get_cache_control().unwrap().unwrap();
+        assert!(cache_control.no_cache());
+        assert_eq!(Some(Duration::from_secs(60)), cache_control.max_age());
+        headers.set_cache_control(&cache_control);
+        assert_eq!(
+            vec!["no-cache, max-age=60"],
+            headers.get_all("Cache-Control")
+        );
+    }
+
+    #[test]
+    fn test_get_and_set_location() {
+        let mut headers = Headers::new();
+        headers.set_location(&"http://example.com/a%20b".parse().unwrap());
+        assert_eq!(
+            "http://example.com/a%20b",
+            headers.get_location().unwrap().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Location header 'a b'")]
+    fn test_get_location_panic_if_malformed() {
+        let mut headers = Headers::new();
+        headers.set("Location", "a b");
+        headers.get_location().unwrap();
+    }
+
+    #[test]
+    fn test_get_and_set_allow() {
+        let mut headers = Headers::new();
+        headers.set_allow(&[Method::Get, Method::Head, Method::Options]);
+        assert_eq!(Some("GET, HEAD, OPTIONS"), headers.get("Allow"));
+        assert_eq!(
+            vec![Method::Get, Method::Head, Method::Options],
+            headers.get_allow().unwrap()
+        );
+        headers.set("Allow", "PUT, PROPFIND");
+        assert_eq!(vec![Method::Put], headers.get_allow().unwrap());
+        headers.set("Allow", "");
+        assert!(headers.get_allow().unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid method 'G/ET' in Allow header")]
+    fn test_get_allow_panic_if_invalid_method() {
+        let mut headers = Headers::new();
+        headers.set("Allow", "G/ET");
+        headers.get_allow().unwrap();
     }
 }
@@ -1,7 +1,59 @@
-use super::{Body, Error, Headers, Message, Result, Status};
+use super::mime::MimeTypes;
+use super::range::Ranges;
+use super::url;
+use super::validators::Validators;
+use super::{Body, Error, Headers, Message, Method, Result, Status};
 use ramhorns::{Content, Template};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Most ranges served from a single request, beyond which the whole file is
+/// sent instead.
+const MAX_RANGES: usize = 16;
+
+/// Policy applied to symbolic links met while resolving a request path.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Symlinks {
+    /// Refuse any path going through a symbolic link
+    Deny,
+    /// Follow symbolic links as long as they point inside the base path
+    #[default]
+    WithinRoot,
+    /// Follow symbolic links wherever they point
+    Follow,
+}
+
+/// Settings of the file and directory listing responses.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// MIME types of the files served
+    pub mime_types: MimeTypes,
+    /// How symbolic links are followed
+    pub symlinks: Symlinks,
+    /// Whether directories are answered with the list of their entries, or are
+    /// forbidden
+    pub listing: bool,
+    /// Whether hidden files, whose name starts with a dot, are listed and
+    /// served
+    pub hidden_files: bool,
+    /// Whether files can be uploaded with `PUT` requests
+    pub uploads: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            mime_types: MimeTypes::default(),
+            symlinks: Symlinks::default(),
+            listing: true,
+            hidden_files: false,
+            uploads: false,
+        }
+    }
+}
 
 #[derive(Debug, Content)]
 struct Entry {
@@ -35,41 +87,281 @@ impl Index {
     }
 }
 
-fn generate_not_found_page(relative_path: impl AsRef<Path>) -> Result<Message> {
-    Ok(Message::new(
-        Status::NotFound,
-        Some(Headers::from([(
-            String::from("Content-Type"),
-            String::from("text/html"),
-        )])),
-        Some(
-            format!(
-                "\
+#[derive(Debug, Content)]
+struct ErrorPage {
+    status: String,
+    title: String,
+    description: String,
+}
+
+impl ErrorPage {
+    fn render(&self) -> Result<String> {
+        let tpl = Template::new(
+            "\
 <html>
     <head>
-        <title>404 Not Found</title>
+        <title>{{status}}</title>
     </head>
     <body>
-        <h2>Not Found</h2>
+        <h2>{{title}}</h2>
 
-        <p>Requested file or directory '{}' could not be found.</p>
+        <p>{{description}}</p>
     </body>
 </html>",
-                relative_path.as_ref().to_string_lossy().to_string()
-            )
-            .parse()?,
-        ),
-    ))
+        )?;
+        Ok(tpl.render(self))
+    }
 }
 
-fn generate_file_response(absolute_path: impl AsRef<Path>) -> Result<Message> {
+/// Generate an HTML page describing an error, titled after the reason phrase
+/// of `status`.
+pub fn generate_error_page(status: Status, description: String) -> Result<Message> {
+    let page = ErrorPage {
+        status: status.to_string(),
+        title: status.reason().unwrap_or("Error").to_string(),
+        description,
+    };
     Ok(Message::new(
-        Status::Ok,
+        status,
         Some(Headers::from([(
             String::from("Content-Type"),
-            String::from("application/octet-stream"),
+            String::from("text/html; charset=utf-8"),
         )])),
-        Some(Body::from(fs::read(absolute_path)?)),
+        Some(page.render()?.parse()?),
+    ))
+}
+
+fn generate_not_found_page(relative_path: impl AsRef<Path>) -> Result<Message> {
+    generate_error_page(
+        Status::NotFound,
+        format!(
+            "Requested file or directory '{}' could not be found.",
+            relative_path.as_ref().to_string_lossy()
+        ),
+    )
+}
+
+fn generate_forbidden_page(relative_path: impl AsRef<Path>) -> Result<Message> {
+    generate_error_page(
+        Status::Forbidden,
+        format!(
+            "Access to file or directory '{}' is forbidden.",
+            relative_path.as_ref().to_string_lossy()
+        ),
+    )
+}
+
+/// Return whether `path` goes through a hidden file or directory.
+fn is_hidden(path: impl AsRef<Path>) -> bool {
+    path.as_ref().components().any(|component| {
+        matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+    })
+}
+
+/// Strip the leading `/` of a request path.
+fn strip_root(relative_path: &Path) -> Result<&Path> {
+    relative_path
+        .strip_prefix("/")
+        .map_err(|_| Error::IndexGeneration(String::from("couldn't strip / from url")))
+}
+
+/// Outcome of the resolution of a request path on the file system.
+enum Resolution {
+    Found(PathBuf),
+    NotFound,
+    Forbidden,
+}
+
+/// Resolve `relative_path` inside `base_path`, making sure that neither `..`
+/// components nor symbolic links lead outside of it, unless allowed by
+/// `symlinks`.
+fn resolve(
+    base_path: impl AsRef<Path>,
+    relative_path: impl AsRef<Path>,
+    symlinks: Symlinks,
+) -> Result<Resolution> {
+    let base_path = base_path.as_ref();
+    // Resolve `.` and `..` components lexically, without going above the base
+    let mut normalized_path = PathBuf::new();
+    for component in relative_path.as_ref().components() {
+        match component {
+            Component::Normal(name) => normalized_path.push(name),
+            Component::CurDir | Component::RootDir => (),
+            Component::ParentDir => {
+                if !normalized_path.pop() {
+                    return Ok(Resolution::Forbidden);
+                }
+            }
+            Component::Prefix(_) => return Ok(Resolution::Forbidden),
+        }
+    }
+    let absolute_path = base_path.join(&normalized_path);
+    match symlinks {
+        Symlinks::Deny => {
+            let mut path = base_path.to_path_buf();
+            for component in normalized_path.components() {
+                path.push(component);
+                match path.symlink_metadata() {
+                    Err(_) => return Ok(Resolution::NotFound),
+                    Ok(metadata) if metadata.file_type().is_symlink() => {
+                        return Ok(Resolution::Forbidden)
+                    }
+                    Ok(_) => (),
+                }
+            }
+        }
+        Symlinks::WithinRoot => match fs::canonicalize(&absolute_path) {
+            Err(_) => return Ok(Resolution::NotFound),
+            Ok(canonical_path) => {
+                if !canonical_path.starts_with(fs::canonicalize(base_path)?) {
+                    return Ok(Resolution::Forbidden);
+                }
+            }
+        },
+        Symlinks::Follow => (),
+    }
+    if !absolute_path.exists() {
+        return Ok(Resolution::NotFound);
+    }
+    Ok(Resolution::Found(absolute_path))
+}
+
+fn generate_file_response(
+    absolute_path: impl AsRef<Path>,
+    request_headers: &Headers,
+    validators: &Validators,
+    options: &Options,
+) -> Result<Message> {
+    let mut file = fs::File::open(&absolute_path)?;
+    let length = file.metadata()?.len() as usize;
+    let content_type = options.mime_types.content_type(&absolute_path, &file);
+    file.rewind()?;
+    let mut headers = file_headers(content_type.clone(), validators);
+    // Ranges only apply if the representation is unchanged according to
+    // If-Range, otherwise the whole file is sent instead
+    let ranges = match request_headers.get("Range") {
+        Some(ranges)
+            if request_headers
+                .get("If-Range")
+                .is_none_or(|v| validators.if_range_matches(v)) =>
+        {
+            // Invalid ranges are ignored as if the header was not there
+            ranges.parse::<Ranges>().ok()
+        }
+        _ => None,
+    };
+    let ranges = match ranges.map(|ranges| ranges.resolve(length)) {
+        Some(ranges) if ranges.len() <= MAX_RANGES => ranges,
+        // Too many ranges are served as the whole file, which is cheaper
+        // than as that many parts
+        _ => {
+            return Ok(Message::new(
+                Status::Ok,
+                Some(headers),
+                Some(Body::from_file(file)?),
+            ))
+        }
+    };
+    match ranges.as_slice() {
+        [] => {
+            headers.set("Content-Range", format!("bytes */{}", length));
+            Ok(Message::new(
+                Status::RangeNotSatisfiable,
+                Some(headers),
+                Some(Body::from(Vec::new())),
+            ))
+        }
+        [range] => {
+            headers.set("Content-Range", content_range(range, length));
+            Ok(Message::new(
+                Status::PartialContent,
+                Some(headers),
+                Some(file_range_body(file, range)?),
+            ))
+        }
+        ranges => {
+            let boundary = format!(
+                "{:x}",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or_default()
+            );
+            headers.set(
+                "Content-Type",
+                format!("multipart/byteranges; boundary={}", boundary),
+            );
+            // Each part is streamed from a clone of the file handle, chained
+            // after the part headers
+            let mut reader: Box<dyn Read + Send> = Box::new(io::empty());
+            let mut body_length = 0;
+            for range in ranges {
+                let part_headers = format!(
+                    "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                    boundary,
+                    content_type,
+                    content_range(range, length)
+                );
+                body_length += part_headers.len() + range.len();
+                let part = FilePart {
+                    file: file.try_clone()?,
+                    start: Some(range.start as u64),
+                };
+                reader = Box::new(
+                    reader
+                        .chain(Cursor::new(part_headers))
+                        .chain(part.take(range.len() as u64)),
+                );
+            }
+            let end = format!("\r\n--{}--\r\n", boundary);
+            body_length += end.len();
+            reader = Box::new(reader.chain(Cursor::new(end)));
+            Ok(Message::new(
+                Status::PartialContent,
+                Some(headers),
+                Some(Body::from_reader(reader, Some(body_length))),
+            ))
+        }
+    }
+}
+
+/// Part of a file starting at `start`, which is only sought on the first read.
+///
+/// Clones of a file handle share its position, so the parts of a multipart
+/// body can't be sought ahead of being read one after the other.
+struct FilePart {
+    file: fs::File,
+    start: Option<u64>,
+}
+
+impl Read for FilePart {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(start) = self.start.take() {
+            self.file.seek(SeekFrom::Start(start))?;
+        }
+        self.file.read(buf)
+    }
+}
+
+/// Return the headers describing a whole file, but its length.
+fn file_headers(content_type: String, validators: &Validators) -> Headers {
+    let mut headers = Headers::from([
+        (String::from("Content-Type"), content_type),
+        (String::from("Accept-Ranges"), String::from("bytes")),
+    ]);
+    validators.set(&mut headers);
+    headers
+}
+
+fn content_range(range: &Range<usize>, length: usize) -> String {
+    format!("bytes {}-{}/{}", range.start, range.end - 1, length)
+}
+
+fn file_range_body(mut file: fs::File, range: &Range<usize>) -> Result<Body> {
+    file.seek(SeekFrom::Start(range.start as u64))?;
+    Ok(Body::from_reader(
+        file.take(range.len() as u64),
+        Some(range.len()),
     ))
 }
 
@@ -77,6 +369,8 @@ fn generate_index_page(
     base_path: impl AsRef<Path>,
     relative_path: impl AsRef<Path>,
     absolute_path: impl AsRef<Path>,
+    validators: &Validators,
+    options: &Options,
 ) -> Result<Message> {
     // Initialize the index structure
     let mut index = Index {
@@ -87,7 +381,7 @@ fn generate_index_page(
     if let Some(parent_path) = absolute_path.as_ref().parent() {
         if let Ok(parent_path) = parent_path.strip_prefix(&base_path) {
             index.entries.push(Entry {
-                url: parent_path.to_string_lossy().to_string(),
+                url: url::encode_path(parent_path),
                 label: String::from(".."),
             })
         }
@@ -95,48 +389,772 @@ fn generate_index_page(
     // For each file/dir entry, add them to the index
     for entry in fs::read_dir(absolute_path)? {
         let dir = entry?;
+        if !options.hidden_files && is_hidden(dir.file_name()) {
+            continue;
+        }
         let path = dir.path();
         index.entries.push(Entry {
-            url: path
-                .strip_prefix(&base_path)
-                .map_err(|_| {
-                    Error::IndexGeneration("couldn't strip base url from url".to_string())
-                })?
-                .to_string_lossy()
-                .to_string(),
+            url: url::encode_path(path.strip_prefix(&base_path).map_err(|_| {
+                Error::IndexGeneration("couldn't strip base url from url".to_string())
+            })?),
             label: dir.file_name().to_string_lossy().to_string(),
         });
     }
     // Render index page
     Ok(Message::new(
         Status::Ok,
-        Some(Headers::from([(
-            String::from("Content-Type"),
-            String::from("text/html"),
-        )])),
+        Some(index_headers(validators)),
         Some(index.render()?.parse()?),
     ))
 }
 
-pub fn generate(base_path: impl AsRef<Path>, relative_path: impl AsRef<Path>) -> Result<Message> {
-    let absolute_path = base_path.as_ref().join(
-        relative_path
-            .as_ref()
-            .strip_prefix(PathBuf::from("/"))
-            .map_err(|_| Error::IndexGeneration(String::from("couldn't strip / from url")))?,
-    );
-    if !absolute_path.exists() {
+/// Return the headers describing a directory listing page, but its length.
+fn index_headers(validators: &Validators) -> Headers {
+    let mut headers = Headers::from([(
+        String::from("Content-Type"),
+        String::from("text/html; charset=utf-8"),
+    )]);
+    validators.set(&mut headers);
+    headers
+}
+
+pub fn generate(
+    base_path: impl AsRef<Path>,
+    relative_path: impl AsRef<Path>,
+    request_headers: &Headers,
+    options: &Options,
+) -> Result<Message> {
+    let stripped_path = strip_root(relative_path.as_ref())?;
+    if !options.hidden_files && is_hidden(stripped_path) {
         return generate_not_found_page(&relative_path);
-    } else if absolute_path.is_file() {
-        return generate_file_response(&absolute_path);
     }
-    generate_index_page(base_path, relative_path, absolute_path)
+    let absolute_path = match resolve(&base_path, stripped_path, options.symlinks)? {
+        Resolution::Found(absolute_path) => absolute_path,
+        Resolution::NotFound => return generate_not_found_page(&relative_path),
+        Resolution::Forbidden => return generate_forbidden_page(&relative_path),
+    };
+    if absolute_path.is_dir() && !options.listing {
+        return generate_forbidden_page(&relative_path);
+    }
+    let validators = Validators::from_metadata(&fs::metadata(&absolute_path)?);
+    if validators.not_modified(request_headers) {
+        let mut headers = Headers::new();
+        validators.set(&mut headers);
+        return Ok(Message::new(Status::NotModified, Some(headers), None));
+    }
+    if absolute_path.is_file() {
+        return generate_file_response(&absolute_path, request_headers, &validators, options);
+    }
+    generate_index_page(
+        base_path,
+        relative_path,
+        absolute_path,
+        &validators,
+        options,
+    )
+}
+
+/// Answer a `HEAD` request with the headers `generate` would send, without
+/// the body, built from the metadata of the resource.
+///
+/// A file is only opened if its extension is unknown, to sniff the same
+/// `Content-Type` as `GET` from its first bytes. A directory listing isn't
+/// rendered, so its `Content-Length` is left out. Range requests are only
+/// defined for `GET`, so `Range` is ignored.
+pub fn generate_head(
+    base_path: impl AsRef<Path>,
+    relative_path: impl AsRef<Path>,
+    request_headers: &Headers,
+    options: &Options,
+) -> Result<Message> {
+    let stripped_path = strip_root(relative_path.as_ref())?;
+    let absolute_path = match resolve(&base_path, stripped_path, options.symlinks)? {
+        Resolution::Found(absolute_path) if options.hidden_files || !is_hidden(stripped_path) => {
+            absolute_path
+        }
+        _ => return generate_without_body(base_path, relative_path, request_headers, options),
+    };
+    let metadata = fs::metadata(&absolute_path)?;
+    let validators = Validators::from_metadata(&metadata);
+    if (metadata.is_dir() && !options.listing) || validators.not_modified(request_headers) {
+        // Nothing is read to answer those, so they are generated as for GET
+        return generate_without_body(base_path, relative_path, request_headers, options);
+    }
+    if metadata.is_file() {
+        let content_type = match options.mime_types.from_extension(&absolute_path) {
+            Some(_) => options.mime_types.content_type(&absolute_path, io::empty()),
+            None => options
+                .mime_types
+                .content_type(&absolute_path, fs::File::open(&absolute_path)?),
+        };
+        let mut headers = file_headers(content_type, &validators);
+        headers.set_content_length(metadata.len() as usize);
+        return Ok(Message::new(Status::Ok, Some(headers), None));
+    }
+    let mut response = Message::new(Status::Ok, Some(index_headers(&validators)), None);
+    response.headers_mut().remove("Content-Length");
+    Ok(response)
+}
+
+fn generate_without_body(
+    base_path: impl AsRef<Path>,
+    relative_path: impl AsRef<Path>,
+    request_headers: &Headers,
+    options: &Options,
+) -> Result<Message> {
+    let mut response = generate(base_path, relative_path, request_headers, options)?;
+    response.take_body();
+    Ok(response)
+}
+
+/// Return the methods supported by the resource at `relative_path`: reading
+/// files and directories, and writing files if uploads are enabled.
+///
+/// The list is empty for a resource that `generate` answers with
+/// `404 Not Found` or `403 Forbidden`, unless it can be uploaded.
+pub fn allowed_methods(
+    base_path: impl AsRef<Path>,
+    relative_path: impl AsRef<Path>,
+    options: &Options,
+) -> Result<Vec<Method>> {
+    let stripped_path = strip_root(relative_path.as_ref())?;
+    if !options.hidden_files && is_hidden(stripped_path) {
+        return Ok(Vec::new());
+    }
+    let read = [Method::Get, Method::Head, Method::Options];
+    Ok(
+        match resolve(&base_path, stripped_path, options.symlinks)? {
+            // Directories are forbidden without listing, and can't be
+            // replaced by an upload either
+            Resolution::Found(path) if path.is_dir() && !options.listing => Vec::new(),
+            Resolution::Found(path) if path.is_dir() => read.to_vec(),
+            Resolution::Found(_) if options.uploads => [&read[..], &[Method::Put]].concat(),
+            Resolution::Found(_) => read.to_vec(),
+            Resolution::NotFound if options.uploads => vec![Method::Options, Method::Put],
+            Resolution::NotFound | Resolution::Forbidden => Vec::new(),
+        },
+    )
+}
+
+/// Answer an `OPTIONS` request with the methods supported by the resource at
+/// `relative_path` in the `Allow` header, or with the page `generate` would
+/// send if there is no such resource.
+pub fn generate_options(
+    base_path: impl AsRef<Path>,
+    relative_path: impl AsRef<Path>,
+    options: &Options,
+) -> Result<Message> {
+    let allowed = allowed_methods(&base_path, &relative_path, options)?;
+    if allowed.is_empty() {
+        return generate(base_path, relative_path, &Headers::new(), options);
+    }
+    let mut headers = Headers::new();
+    headers.set_allow(&allowed);
+    Ok(Message::new(
+        Status::Ok,
+        Some(headers),
+        Some(Body::from(Vec::new())),
+    ))
+}
+
+/// Store the body of a `PUT` request as the file at `relative_path`, answering
+/// `201 Created` for a new file or `204 No Content` for a replaced one.
+///
+/// The parent directory must already exist, and the path is resolved with the
+/// same rules as for `generate`, so that no file is written outside of
+/// `base_path`.
+pub fn upload(
+    base_path: impl AsRef<Path>,
+    relative_path: impl AsRef<Path>,
+    body: Option<&Body>,
+    options: &Options,
+) -> Result<Message> {
+    let stripped_path = strip_root(relative_path.as_ref())?;
+    let Some(file_name) = stripped_path.file_name() else {
+        return generate_error_page(
+            Status::Conflict,
+            String::from("A directory can't be replaced by an upload."),
+        );
+    };
+    if !options.hidden_files && is_hidden(stripped_path) {
+        return generate_forbidden_page(&relative_path);
+    }
+    let parent_path = stripped_path.parent().unwrap_or(Path::new(""));
+    let absolute_path = match resolve(&base_path, parent_path, options.symlinks)? {
+        Resolution::Found(parent) if parent.is_dir() => parent.join(file_name),
+        Resolution::Found(_) | Resolution::NotFound => {
+            return generate_error_page(
+                Status::Conflict,
+                format!(
+                    "Directory '/{}' doesn't exist.",
+                    parent_path.to_string_lossy()
+                ),
+            )
+        }
+        Resolution::Forbidden => return generate_forbidden_page(&relative_path),
+    };
+    // The file itself may be a symbolic link leading elsewhere
+    let status = match resolve(&base_path, stripped_path, options.symlinks)? {
+        Resolution::Found(path) if path.is_dir() => {
+            return generate_error_page(
+                Status::Conflict,
+                String::from("A directory can't be replaced by an upload."),
+            )
+        }
+        Resolution::Found(_) => Status::NoContent,
+        // A dangling symbolic link would be followed when writing the file
+        Resolution::NotFound
+            if options.symlinks != Symlinks::Follow && absolute_path.symlink_metadata().is_ok() =>
+        {
+            return generate_forbidden_page(&relative_path)
+        }
+        Resolution::NotFound => Status::Created,
+        Resolution::Forbidden => return generate_forbidden_page(&relative_path),
+    };
+    let content = body
+        .map_or(Some(&[][..]), Body::as_bytes)
+        .ok_or_else(|| Error::IndexGeneration(String::from("couldn't upload a streamed body")))?;
+    fs::write(&absolute_path, content)?;
+    // Unlike a 204, a 201 may have a body, whose empty length must be given
+    // for the connection to be kept alive
+    let body = (status == Status::Created).then(|| Body::from(Vec::new()));
+    Ok(Message::new(status, None, body))
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+
+    const FILE_EXAMPLE: &str = "hello world";
+
+    /// Base directory holding `file.txt`, unique to the calling test and
+    /// removed along with its content once dropped.
+    struct BasePath(PathBuf);
+
+    impl std::ops::Deref for BasePath {
+        type Target = Path;
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for BasePath {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for BasePath {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.0).ok();
+        }
+    }
+
+    /// Create a fresh base directory, named after the calling test and the
+    /// process so that concurrent runs don't share it.
+    fn setup_base_path(name: &str) -> BasePath {
+        let base_path = BasePath(std::env::temp_dir().join(format!(
+            "webserver_test_index_{}_{}",
+            name,
+            std::process::id()
+        )));
+        fs::remove_dir_all(&*base_path).ok();
+        fs::create_dir_all(&*base_path).unwrap();
+        fs::write(base_path.join("file.txt"), FILE_EXAMPLE).unwrap();
+        base_path
+    }
+
+    fn generate_to_string(base_path: &Path, request_headers: &[(&str, &str)]) -> String {
+        generate_path_to_string(base_path, "/file.txt", request_headers)
+    }
+
+    fn generate_path_to_string(
+        base_path: &Path,
+        relative_path: &str,
+        request_headers: &[(&str, &str)],
+    ) -> String {
+        generate_with_options_to_string(
+            base_path,
+            relative_path,
+            request_headers,
+            &Options::default(),
+        )
+    }
+
+    fn generate_with_options_to_string(
+        base_path: &Path,
+        relative_path: &str,
+        request_headers: &[(&str, &str)],
+        options: &Options,
+    ) -> String {
+        let mut headers = Headers::new();
+        for (k, v) in request_headers {
+            headers.set(*k, *v);
+        }
+        let mut buffer = Vec::new();
+        generate(base_path, relative_path, &headers, options)
+            .unwrap()
+            .write(&mut buffer)
+            .unwrap();
+        String::from_utf8_lossy(&buffer).to_string()
+    }
+
+    #[test]
+    fn test_generate_directory_encodes_links() {
+        let base_path = setup_base_path("encodes_links");
+        fs::create_dir_all(base_path.join("my dir")).unwrap();
+        fs::write(base_path.join("my dir").join("a b#?%.txt"), "").unwrap();
+        let response = generate_path_to_string(&base_path, "/my dir", &[]);
+        assert!(response.contains("<a href=\"/my%20dir/a%20b%23%3F%25.txt\">a b#?%.txt</a>"));
+        assert!(response.contains("<a href=\"/\">..</a>"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate_directory_encodes_non_utf8_links() {
+        use std::os::unix::ffi::OsStrExt;
+        let base_path = setup_base_path("encodes_non_utf8_links");
+        fs::write(base_path.join(std::ffi::OsStr::from_bytes(b"caf\xe9")), "").unwrap();
+        let response = generate_path_to_string(&base_path, "/", &[]);
+        assert!(response.contains("<a href=\"/caf%E9\">"));
+    }
+
+    #[test]
+    fn test_generate_not_found() {
+        let base_path = setup_base_path("not_found");
+        let response = generate_path_to_string(&base_path, "/<missing>", &[]);
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(response.contains("&lt;missing&gt;"));
+    }
+
+    #[test]
+    fn test_generate_forbidden_if_path_escapes_base() {
+        let base_path = setup_base_path("escapes_base");
+        for path in ["/../file.txt", "/a/../../file.txt", "/.."] {
+            let response = generate_path_to_string(&base_path, path, &[]);
+            assert!(
+                response.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+                "{}",
+                path
+            );
+        }
+        let response = generate_path_to_string(&base_path, "/a/./../file.txt", &[]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate_symlinks_policy() {
+        let base_path = setup_base_path("symlinks");
+        let outside_path = setup_base_path("symlinks_outside");
+        for (link, target) in [
+            ("inside", base_path.join("file.txt")),
+            ("outside", outside_path.to_path_buf()),
+        ] {
+            std::os::unix::fs::symlink(target, base_path.join(link)).unwrap();
+        }
+        let status = |path: &str, symlinks: Symlinks| {
+            let options = Options {
+                symlinks,
+                ..Options::default()
+            };
+            let response = generate_with_options_to_string(&base_path, path, &[], &options);
+            response.split("\r\n").next().unwrap().to_string()
+        };
+        assert_eq!("HTTP/1.1 200 OK", status("/inside", Symlinks::WithinRoot));
+        assert_eq!(
+            "HTTP/1.1 403 Forbidden",
+            status("/outside/file.txt", Symlinks::WithinRoot)
+        );
+        assert_eq!(
+            "HTTP/1.1 403 Forbidden",
+            status("/outside", Symlinks::WithinRoot)
+        );
+        assert_eq!("HTTP/1.1 403 Forbidden", status("/inside", Symlinks::Deny));
+        assert_eq!(
+            "HTTP/1.1 403 Forbidden",
+            status("/outside/file.txt", Symlinks::Deny)
+        );
+        assert_eq!("HTTP/1.1 200 OK", status("/file.txt", Symlinks::Deny));
+        assert_eq!(
+            "HTTP/1.1 200 OK",
+            status("/outside/file.txt", Symlinks::Follow)
+        );
+    }
+
+    #[test]
+    fn test_generate_file_advertises_ranges() {
+        let base_path = setup_base_path("advertises_ranges");
+        let response = generate_to_string(&base_path, &[]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Accept-Ranges: bytes\r\n"));
+        assert!(response.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello world"));
+    }
+
+    #[test]
+    fn test_generate_head() {
+        let base_path = setup_base_path("head");
+        // The type of an extensionless file is sniffed alike
+        fs::write(base_path.join("page"), "<!DOCTYPE html><p>hi</p>").unwrap();
+        // Range is ignored, so the headers are the ones of the whole file
+        let mut headers = Headers::new();
+        headers.set("Range", "bytes=6-");
+        for path in ["/file.txt", "/page", "/", "/missing"] {
+            let mut get = Vec::new();
+            generate(&base_path, path, &Headers::new(), &Options::default())
+                .unwrap()
+                .write(&mut get)
+                .unwrap();
+            let mut head = generate_head(&base_path, path, &headers, &Options::default()).unwrap();
+            assert_eq!(&None, head.body());
+            let mut buffer = Vec::new();
+            head.write(&mut buffer).unwrap();
+            let get = String::from_utf8_lossy(&get).to_string();
+            let (get_head, _) = get.split_once("\r\n\r\n").unwrap();
+            assert!(get_head.contains("Content-Length: "));
+            let expected = if path == "/" {
+                // The listing isn't rendered, so its length is unknown
+                get_head
+                    .split("\r\n")
+                    .filter(|line| !line.starts_with("Content-Length: "))
+                    .collect::<Vec<_>>()
+                    .join("\r\n")
+            } else {
+                get_head.to_string()
+            };
+            assert_eq!(
+                format!("{}\r\n\r\n", expected),
+                String::from_utf8_lossy(&buffer)
+            );
+        }
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let base_path = setup_base_path("allowed_methods");
+        fs::write(base_path.join(".hidden"), "").unwrap();
+        let uploads = Options {
+            uploads: true,
+            ..Options::default()
+        };
+        let read = vec![Method::Get, Method::Head, Method::Options];
+        let allowed =
+            |path: &str, options: &Options| allowed_methods(&base_path, path, options).unwrap();
+        assert_eq!(read, allowed("/file.txt", &Options::default()));
+        assert_eq!(read, allowed("/", &uploads));
+        assert_eq!(
+            vec![Method::Get, Method::Head, Method::Options, Method::Put],
+            allowed("/file.txt", &uploads)
+        );
+        assert_eq!(
+            vec![Method::Options, Method::Put],
+            allowed("/new.txt", &uploads)
+        );
+        assert!(allowed("/new.txt", &Options::default()).is_empty());
+        assert!(allowed("/.hidden", &uploads).is_empty());
+        let no_listing = Options {
+            listing: false,
+            uploads: true,
+            ..Options::default()
+        };
+        assert!(allowed("/", &no_listing).is_empty());
+        assert_eq!(
+            vec![Method::Get, Method::Head, Method::Options, Method::Put],
+            allowed("/file.txt", &no_listing)
+        );
+        let response = generate_options(&base_path, "/", &no_listing).unwrap();
+        assert_eq!(Some(Status::Forbidden), response.status());
+    }
+
+    #[test]
+    fn test_options() {
+        let base_path = setup_base_path("options");
+        let mut buffer = Vec::new();
+        generate_options(&base_path, "/file.txt", &Options::default())
+            .unwrap()
+            .write(&mut buffer)
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nAllow: GET, HEAD, OPTIONS\r\nContent-Length: 0\r\n\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+        let response = generate_options(&base_path, "/missing", &Options::default()).unwrap();
+        assert_eq!(Some(Status::NotFound), response.status());
+    }
+
+    #[test]
+    fn test_generate_file_single_range() {
+        let base_path = setup_base_path("single_range");
+        let response = generate_to_string(&base_path, &[("Range", "bytes=6-")]);
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert!(response.contains("Content-Range: bytes 6-10/11\r\n"));
+        assert!(response.contains("Content-Length: 5\r\n"));
+        assert!(response.ends_with("\r\n\r\nworld"));
+    }
+
+    #[test]
+    fn test_generate_file_multiple_ranges() {
+        let base_path = setup_base_path("multiple_ranges");
+        let response = generate_to_string(&base_path, &[("Range", "bytes=0-4,-5")]);
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        let boundary = response
+            .split("multipart/byteranges; boundary=")
+            .nth(1)
+            .and_then(|s| s.split("\r\n").next())
+            .unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains(&format!("Content-Length: {}", body.len())));
+        assert_eq!(
+            format!(
+                "\r\n--{0}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 0-4/11\r\n\r\nhello\
+                 \r\n--{0}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 6-10/11\r\n\r\nworld\
+                 \r\n--{0}--\r\n",
+                boundary
+            ),
+            body
+        );
+    }
+
+    #[test]
+    fn test_generate_file_overlapping_ranges() {
+        let base_path = setup_base_path("overlapping_ranges");
+        let response = generate_to_string(&base_path, &[("Range", "bytes=2-6,0-4")]);
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert!(response.contains("Content-Range: bytes 0-6/11\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello w"));
+    }
+
+    #[test]
+    fn test_generate_file_too_many_ranges() {
+        let base_path = setup_base_path("too_many_ranges");
+        fs::write(base_path.join("large.txt"), "a".repeat(100)).unwrap();
+        let ranges: Vec<_> = (0..=MAX_RANGES)
+            .map(|i| format!("{0}-{0}", i * 2))
+            .collect();
+        let response = generate_path_to_string(
+            &base_path,
+            "/large.txt",
+            &[("Range", &format!("bytes={}", ranges.join(",")))],
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Length: 100\r\n"));
+    }
+
+    #[test]
+    fn test_generate_file_unsatisfiable_range() {
+        let base_path = setup_base_path("unsatisfiable_range");
+        let response = generate_to_string(&base_path, &[("Range", "bytes=20-")]);
+        assert!(response.starts_with("HTTP/1.1 416 Range Not Satisfiable\r\n"));
+        assert!(response.contains("Content-Range: bytes */11\r\n"));
+    }
+
+    #[test]
+    fn test_generate_file_ignore_invalid_range() {
+        let base_path = setup_base_path("invalid_range");
+        let response = generate_to_string(&base_path, &[("Range", "bytes=5-1")]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    fn header_value(response: &str, name: &str) -> String {
+        let prefix = format!("\r\n{}: ", name);
+        response
+            .split(&prefix)
+            .nth(1)
+            .unwrap()
+            .split("\r\n")
+            .next()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_generate_file_with_validators() {
+        let base_path = setup_base_path("file_validators");
+        let response = generate_to_string(&base_path, &[]);
+        let etag = header_value(&response, "ETag");
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        let last_modified = header_value(&response, "Last-Modified");
+        assert!(last_modified.ends_with(" GMT"));
+    }
+
+    #[test]
+    fn test_generate_directory_with_validators() {
+        let base_path = setup_base_path("directory_validators");
+        let response = generate_path_to_string(&base_path, "/", &[]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\nETag: \""));
+        assert!(response.contains("\r\nLast-Modified: "));
+    }
+
+    #[test]
+    fn test_generate_not_modified_if_none_match() {
+        let base_path = setup_base_path("if_none_match");
+        let etag = header_value(&generate_to_string(&base_path, &[]), "ETag");
+        let response = generate_to_string(&base_path, &[("If-None-Match", &etag)]);
+        assert!(response.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        assert!(response.contains(&format!("ETag: {}\r\n", etag)));
+        assert!(response.ends_with("\r\n\r\n"));
+        assert!(!response.contains("Content-Length"));
+    }
+
+    #[test]
+    fn test_generate_modified_if_none_match_differs() {
+        let base_path = setup_base_path("if_none_match_differs");
+        let response = generate_to_string(&base_path, &[("If-None-Match", "\"x\"")]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_generate_not_modified_since() {
+        let base_path = setup_base_path("if_modified_since");
+        let last_modified = header_value(&generate_to_string(&base_path, &[]), "Last-Modified");
+        let response = generate_to_string(&base_path, &[("If-Modified-Since", &last_modified)]);
+        assert!(response.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        let response = generate_to_string(
+            &base_path,
+            &[("If-Modified-Since", "Thu, 01 Jan 1970 00:00:00 GMT")],
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_generate_file_range_if_range_matches() {
+        let base_path = setup_base_path("if_range_matches");
+        let etag = header_value(&generate_to_string(&base_path, &[]), "ETag");
+        let response =
+            generate_to_string(&base_path, &[("Range", "bytes=0-4"), ("If-Range", &etag)]);
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn test_generate_file_ignore_range_if_range_does_not_match() {
+        let base_path = setup_base_path("if_range");
+        let response = generate_to_string(
+            &base_path,
+            &[("Range", "bytes=0-4"), ("If-Range", "\"outdated\"")],
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello world"));
+    }
+    #[test]
+    fn test_generate_without_listing() {
+        let base_path = setup_base_path("listing");
+        let options = Options {
+            listing: false,
+            ..Options::default()
+        };
+        let response = generate_with_options_to_string(&base_path, "/", &[], &options);
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        let response = generate_with_options_to_string(&base_path, "/file.txt", &[], &options);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_generate_hidden_files() {
+        let base_path = setup_base_path("hidden");
+        fs::create_dir_all(base_path.join(".git")).unwrap();
+        fs::write(base_path.join(".git").join("config"), "secret").unwrap();
+        let response = generate_path_to_string(&base_path, "/", &[]);
+        assert!(!response.contains(".git"));
+        let response = generate_path_to_string(&base_path, "/.git/config", &[]);
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let options = Options {
+            hidden_files: true,
+            ..Options::default()
+        };
+        let response = generate_with_options_to_string(&base_path, "/", &[], &options);
+        assert!(response.contains(">.git<"));
+        let response = generate_with_options_to_string(&base_path, "/.git/config", &[], &options);
+        assert!(response.ends_with("\r\n\r\nsecret"));
+    }
+
+    fn upload_status(
+        base_path: &Path,
+        relative_path: &str,
+        content: &str,
+        options: &Options,
+    ) -> Status {
+        upload(
+            base_path,
+            relative_path,
+            Some(&Body::from(content)),
+            options,
+        )
+        .unwrap()
+        .status()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_upload() {
+        let base_path = setup_base_path("upload");
+        let options = Options {
+            uploads: true,
+            ..Options::default()
+        };
+        let mut buffer = Vec::new();
+        upload(&base_path, "/new.txt", Some(&Body::from("new")), &options)
+            .unwrap()
+            .write(&mut buffer)
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+        assert_eq!(
+            "new",
+            fs::read_to_string(base_path.join("new.txt")).unwrap()
+        );
+        assert_eq!(
+            Status::NoContent,
+            upload_status(&base_path, "/new.txt", "newer", &options)
+        );
+        assert_eq!(
+            "newer",
+            fs::read_to_string(base_path.join("new.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_upload_refused() {
+        let base_path = setup_base_path("upload_refused");
+        let options = Options::default();
+        assert_eq!(
+            Status::Conflict,
+            upload_status(&base_path, "/missing/new.txt", "", &options)
+        );
+        assert_eq!(
+            Status::Conflict,
+            upload_status(&base_path, "/", "", &options)
+        );
+        assert_eq!(
+            Status::Forbidden,
+            upload_status(&base_path, "/../new.txt", "", &options)
+        );
+        assert_eq!(
+            Status::Forbidden,
+            upload_status(&base_path, "/.hidden", "", &options)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_upload_refused_through_dangling_symlink() {
+        let base_path = setup_base_path("upload_dangling");
+        let outside_path = setup_base_path("upload_dangling_outside");
+        std::os::unix::fs::symlink(outside_path.join("created.txt"), base_path.join("dangling"))
+            .unwrap();
+        assert_eq!(
+            Status::Forbidden,
+            upload_status(&base_path, "/dangling", "", &Options::default())
+        );
+        assert!(!outside_path.join("created.txt").exists());
+    }
+
     #[test]
     fn test_index_generation() {
         assert_eq!(
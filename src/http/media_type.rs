@@ -0,0 +1,172 @@
+use super::headers::{is_valid_name, quote, split_quoted, unquote};
+use super::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A media type such as `text/html; charset=utf-8`, as sent in `Content-Type`
+/// and `Accept` headers.
+///
+/// Type, subtype and parameter names are case-insensitive, so they are stored
+/// in lowercase. Parameter values keep their case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaType {
+    type_: String,
+    subtype: String,
+    parameters: Vec<(String, String)>,
+}
+
+impl MediaType {
+    pub fn new(type_: &str, subtype: &str) -> Self {
+        Self {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Add the parameter `name`, replacing any previous value.
+    pub fn with_parameter(mut self, name: &str, value: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.parameters.retain(|(n, _)| *n != name);
+        self.parameters.push((name, value.to_string()));
+        self
+    }
+
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Return the media type without its parameters, such as `text/html`.
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn parameters(&self) -> &[(String, String)] {
+        &self.parameters
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.parameter("charset")
+    }
+
+    /// Return whether this media type falls within `range`, which may use
+    /// wildcards such as `text/*` or `*/*`. Parameters of the range must all be
+    /// matched.
+    pub fn matches(&self, range: &MediaType) -> bool {
+        (range.type_ == "*" || range.type_ == self.type_)
+            && (range.subtype == "*" || range.subtype == self.subtype)
+            && range
+                .parameters
+                .iter()
+                .all(|(n, v)| self.parameter(n).is_some_and(|p| p.eq_ignore_ascii_case(v)))
+    }
+}
+
+/// Parse `; name=value` parameters, whose values may be quoted strings.
+pub(super) fn parse_parameters<'a>(
+    parameters: impl Iterator<Item = &'a str>,
+) -> Option<Vec<(String, String)>> {
+    parameters
+        .map(|parameter| parameter.trim_matches([' ', '\t']))
+        .filter(|parameter| !parameter.is_empty())
+        .map(|parameter| {
+            let (name, value) = parameter.split_once('=')?;
+            let value = if value.starts_with('"') {
+                unquote(value)?
+            } else if is_valid_name(value) {
+                value.to_string()
+            } else {
+                return None;
+            };
+            is_valid_name(name).then(|| (name.to_ascii_lowercase(), value))
+        })
+        .collect()
+}
+
+impl FromStr for MediaType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || Error::MalformedHeaders(format!("invalid media type '{}'", s));
+        let mut parts = split_quoted(s, ';').into_iter();
+        let essence = parts.next().unwrap_or_default().trim_matches([' ', '\t']);
+        let (type_, subtype) = essence.split_once('/').ok_or_else(malformed)?;
+        if !is_valid_name(type_) || !is_valid_name(subtype) {
+            return Err(malformed());
+        }
+        Ok(Self {
+            parameters: parse_parameters(parts).ok_or_else(malformed)?,
+            ..Self::new(type_, subtype)
+        })
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.parameters {
+            write!(f, "; {}={}", name, quote(value))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let media_type: MediaType = "Text/HTML; Charset=\"UTF-8\" ;level=1".parse().unwrap();
+        assert_eq!("text", media_type.type_());
+        assert_eq!("html", media_type.subtype());
+        assert_eq!("text/html", media_type.essence());
+        assert_eq!(Some("UTF-8"), media_type.charset());
+        assert_eq!(Some("1"), media_type.parameter("LEVEL"));
+        assert_eq!(None, media_type.parameter("q"));
+    }
+
+    #[test]
+    fn test_display() {
+        let media_type = MediaType::new("multipart", "byteranges")
+            .with_parameter("boundary", "a b")
+            .with_parameter("charset", "utf-8");
+        assert_eq!(
+            "multipart/byteranges; boundary=\"a b\"; charset=utf-8",
+            media_type.to_string()
+        );
+        assert_eq!(media_type, media_type.to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn test_matches() {
+        let media_type: MediaType = "text/html; charset=utf-8".parse().unwrap();
+        assert!(media_type.matches(&"*/*".parse().unwrap()));
+        assert!(media_type.matches(&"text/*".parse().unwrap()));
+        assert!(media_type.matches(&"text/html; charset=UTF-8".parse().unwrap()));
+        assert!(!media_type.matches(&"text/plain".parse().unwrap()));
+        assert!(!media_type.matches(&"text/html; level=1".parse().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid media type 'text'")]
+    fn test_from_str_panic_if_no_subtype() {
+        "text".parse::<MediaType>().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid media type")]
+    fn test_from_str_panic_if_malformed_parameter() {
+        "text/plain; charset".parse::<MediaType>().unwrap();
+    }
+}
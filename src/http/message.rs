@@ -1,5 +1,7 @@
+use super::headers::is_valid_name;
 use super::{Body, Error, Headers, Method, Result, Status, Url, Version};
-use std::io::{BufRead, Write};
+use std::fmt;
+use std::io::{BufRead, Read, Write};
 use std::str::FromStr;
 
 #[derive(Debug, PartialEq)]
@@ -12,30 +14,34 @@ pub enum StartLine {
     Response {
         version: Version,
         status: Status,
+        reason: String,
     },
 }
 
-impl FromStr for StartLine {
-    type Err = Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl StartLine {
+    fn parse_request_line(s: &str) -> Result<Self> {
         let mut space_splitted_iter = s.split_ascii_whitespace();
 
         let method = space_splitted_iter
             .next()
             .ok_or(Error::MalformedRequestLine(
                 "couldn't find the method".to_string(),
-            ))?
-            .parse()
-            .map_err(|e| {
+            ))?;
+        let method = method.parse().map_err(|e| {
+            // A well-formed method is valid, only unknown to the server
+            if is_valid_name(method) {
+                Error::NotImplemented(format!("method '{}'", method))
+            } else {
                 Error::MalformedRequestLine(format!("couldn't parse given method: {}", e))
-            })?;
+            }
+        })?;
 
         let url = space_splitted_iter
             .next()
             .ok_or(Error::MalformedRequestLine(
                 "couldn't find the url".to_string(),
             ))?
-            .to_string();
+            .parse()?;
 
         let version = space_splitted_iter
             .next()
@@ -48,25 +54,182 @@ impl FromStr for StartLine {
             })?;
 
         Ok(Self::Request {
-            method: method,
-            url: url,
-            version: version,
+            method,
+            url,
+            version,
+        })
+    }
+
+    fn parse_status_line(s: &str) -> Result<Self> {
+        // The reason phrase may hold spaces, or be missing altogether
+        let mut space_splitted_iter = s.splitn(3, ' ');
+
+        let version = space_splitted_iter
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|e| {
+                Error::MalformedStatusLine(format!("couldn't parse given version: {}", e))
+            })?;
+
+        let status = space_splitted_iter
+            .next()
+            .ok_or(Error::MalformedStatusLine(
+                "couldn't find the status code".to_string(),
+            ))?
+            .parse()?;
+
+        let reason = space_splitted_iter.next().unwrap_or_default().to_string();
+
+        Ok(Self::Response {
+            version,
+            status,
+            reason,
         })
     }
 }
 
-impl ToString for StartLine {
-    fn to_string(&self) -> String {
+/// Parse either a request line or, if it starts with an HTTP version, a status
+/// line.
+impl FromStr for StartLine {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("HTTP/") {
+            Self::parse_status_line(s)
+        } else {
+            Self::parse_request_line(s)
+        }
+    }
+}
+
+impl fmt::Display for StartLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Request {
                 method,
                 url,
                 version,
-            } => format!("{} {} {}\r\n", method, url, version),
-            Self::Response { version, status } => {
-                format!("{} {}\r\n", version, status)
-            }
+            } => write!(f, "{} {} {}\r\n", method, url, version),
+            Self::Response {
+                version,
+                status,
+                reason,
+            } => write!(f, "{} {} {}\r\n", version, status.code(), reason),
+        }
+    }
+}
+
+/// How messages are read.
+///
+/// Limits bound the memory spent on a message, whose sizes are otherwise
+/// chosen by the peer.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Follow RFC 9112 to the letter, rejecting anything it allows recipients
+    /// to tolerate along with messages whose framing is ambiguous. This
+    /// protects against request smuggling behind a proxy that would frame
+    /// them differently.
+    pub strict: bool,
+    /// Maximum length of the start line, beyond which a request is answered
+    /// with `414 URI Too Long`
+    pub max_start_line_length: usize,
+    /// Maximum size of the header section, or of the trailer section of a
+    /// chunked body, beyond which a request is answered with
+    /// `431 Request Header Fields Too Large`
+    pub max_header_size: usize,
+    /// Maximum number of header or trailer fields, also answered with `431`
+    pub max_headers: usize,
+    /// Maximum size of the body once decoded, beyond which a request is
+    /// answered with `413 Content Too Large`
+    pub max_body_size: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            max_start_line_length: 8 * 1024,
+            max_header_size: 64 * 1024,
+            max_headers: 100,
+            max_body_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Read a line ending with CRLF, or with a bare LF unless `strict`, and return
+/// it without its line ending. Return `None` at the end of the stream.
+///
+/// Lines longer than `limit`, line ending aside, fail with `too_long`. Bare
+/// CRs within the line are rejected if `strict`, and replaced with spaces
+/// otherwise.
+pub(super) fn read_line(
+    bufread: &mut impl BufRead,
+    limit: usize,
+    too_long: fn(String) -> Error,
+    strict: bool,
+) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    let read =
+        Read::take(&mut *bufread, limit.saturating_add(2) as u64).read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.pop() != Some(b'\n') {
+        if read as u64 == limit.saturating_add(2) as u64 {
+            return Err(too_long(format!("line is longer than {} bytes", limit)));
         }
+        return Err(Error::MalformedHeaders(
+            "connection closed in the middle of the head".to_string(),
+        ));
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    } else if strict {
+        return Err(Error::MalformedHeaders(
+            "line doesn't end with CRLF".to_string(),
+        ));
+    }
+    if line.len() > limit {
+        return Err(too_long(format!("line is longer than {} bytes", limit)));
+    }
+    if line.contains(&b'\r') {
+        if strict {
+            return Err(Error::MalformedHeaders("line holds a bare CR".to_string()));
+        }
+        line.iter_mut()
+            .filter(|b| **b == b'\r')
+            .for_each(|b| *b = b' ');
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// Read header fields up to the empty line ending them, or up to the end of
+/// the stream.
+pub(super) fn read_headers(bufread: &mut impl BufRead, options: &ParseOptions) -> Result<Headers> {
+    let mut lines = Vec::new();
+    let mut remaining = options.max_header_size;
+    while let Some(line) = read_line(
+        bufread,
+        remaining,
+        Error::HeaderFieldsTooLarge,
+        options.strict,
+    )? {
+        if line.is_empty() {
+            break;
+        }
+        if lines.len() == options.max_headers {
+            return Err(Error::HeaderFieldsTooLarge(format!(
+                "more than {} fields",
+                options.max_headers
+            )));
+        }
+        remaining = remaining.saturating_sub(line.len() + 2);
+        lines.push(line);
+    }
+    if options.strict {
+        Headers::read_strict(lines.into_iter())
+    } else {
+        Headers::read(lines.into_iter())
     }
 }
 
@@ -75,55 +238,215 @@ pub struct Message {
     start_line: StartLine,
     headers: Headers,
     body: Option<Body>,
+    trailers: Headers,
 }
 
 impl Message {
     pub fn read(bufread: &mut impl BufRead) -> Result<Self> {
-        // Parse header
-        let mut iter = bufread
-            .by_ref()
-            .lines()
-            .take_while(|s| s.is_ok())
-            .map(|s| s.unwrap());
-        let start_line = iter
-            .next()
-            .ok_or(Error::MalformedRequestLine(
-                "couldn't find request line".to_string(),
-            ))?
-            .parse()?;
-        let headers = Headers::read(iter)?;
-        let body = headers
-            .get_content_length()
-            .map(|content_length| Body::read(bufread, content_length))
-            .transpose()?;
-        Ok(Self {
-            start_line: start_line,
-            headers: headers,
-            body: body,
-        })
+        Self::read_with(bufread, &ParseOptions::default())
+    }
+
+    pub fn read_with(bufread: &mut impl BufRead, options: &ParseOptions) -> Result<Self> {
+        let mut message = Self::read_head_with(bufread, options)?;
+        message.read_body_with(bufread, options)?;
+        Ok(message)
     }
 
-    pub fn write(&self, bufwrite: &mut impl Write) -> Result<()> {
-        bufwrite.write(self.start_line.to_string().as_bytes())?;
+    /// Read the start line and headers of a message, leaving its body in
+    /// `bufread`.
+    pub fn read_head(bufread: &mut impl BufRead) -> Result<Self> {
+        Self::read_head_with(bufread, &ParseOptions::default())
+    }
+
+    pub fn read_head_with(bufread: &mut impl BufRead, options: &ParseOptions) -> Result<Self> {
+        let start_line = read_line(
+            bufread,
+            options.max_start_line_length,
+            Error::UriTooLong,
+            options.strict,
+        )?
+        .ok_or(Error::MalformedRequestLine(
+            "couldn't find request line".to_string(),
+        ))?;
+        if options.strict && !start_line.starts_with("HTTP/") {
+            // Exactly one space is allowed between the parts of a request line
+            if start_line.split(' ').count() != 3 || start_line.split(' ').any(str::is_empty) {
+                return Err(Error::MalformedRequestLine(format!(
+                    "invalid request line '{}'",
+                    start_line
+                )));
+            }
+        }
+        let message = Self {
+            start_line: start_line.parse()?,
+            headers: read_headers(bufread, options)?,
+            body: None,
+            trailers: Headers::new(),
+        };
+        if options.strict {
+            message.check_framing()?;
+        }
+        Ok(message)
+    }
+
+    /// Reject messages whose framing is ambiguous, which RFC 9112 allows to
+    /// be resolved in ways that intermediaries may not agree on, along with
+    /// HTTP/1.1 requests lacking a single valid `Host`.
+    fn check_framing(&self) -> Result<()> {
+        let malformed = |reason: &str| Err(Error::MalformedHeaders(reason.to_string()));
+        // Checked even if the message can't have a body
+        let length = self.headers.get_content_length()?;
+        if length.is_some() && self.headers.contains("Transfer-Encoding") {
+            return malformed("both Content-Length and Transfer-Encoding are present");
+        }
+        if let StartLine::Request { version, .. } = &self.start_line {
+            if self.headers.contains("Transfer-Encoding") && !self.headers.is_chunked() {
+                return malformed("chunked isn't the final transfer coding");
+            }
+            match self.headers.get_all("Host").len() {
+                0 if *version == Version::V1_1 => return malformed("missing Host header"),
+                0 | 1 => (),
+                _ => return malformed("multiple Host headers"),
+            }
+            self.headers.get_host()?;
+        }
+        Ok(())
+    }
+
+    /// Read the body following the head of the message, framed according to
+    /// its headers.
+    ///
+    /// Requests without framing headers have no body, whereas responses
+    /// without them have a body delimited by the end of the connection.
+    pub fn read_body(&mut self, bufread: &mut impl BufRead) -> Result<()> {
+        self.read_body_with(bufread, &ParseOptions::default())
+    }
+
+    pub fn read_body_with(
+        &mut self,
+        bufread: &mut impl BufRead,
+        options: &ParseOptions,
+    ) -> Result<()> {
+        if !Self::may_have_body(&self.start_line) {
+            return Ok(());
+        }
+        if self.headers.is_chunked() {
+            let (body, trailers) = Body::read_chunked(bufread, options)?;
+            self.body = Some(body);
+            self.trailers = trailers;
+        } else if let Some(content_length) = self.headers.get_content_length()? {
+            if content_length > options.max_body_size {
+                return Err(Error::ContentTooLarge(format!(
+                    "body of {} bytes is larger than {} bytes",
+                    content_length, options.max_body_size
+                )));
+            }
+            self.body = Some(Body::read(bufread, content_length)?);
+        } else if let StartLine::Response { .. } = self.start_line {
+            self.body = Some(Body::read_to_end(bufread, options.max_body_size)?);
+        }
+        Ok(())
+    }
+
+    /// Return whether a message may have a body, which is never the case for
+    /// informational, `204 No Content` and `304 Not Modified` responses.
+    pub(super) fn may_have_body(start_line: &StartLine) -> bool {
+        match start_line {
+            StartLine::Request { .. } => true,
+            StartLine::Response { status, .. } => {
+                !(status.is_informational()
+                    || *status == Status::NoContent
+                    || *status == Status::NotModified)
+            }
+        }
+    }
+
+    /// Write the message, consuming its body if it is streamed.
+    pub fn write(&mut self, bufwrite: &mut impl Write) -> Result<()> {
+        bufwrite.write_all(self.start_line.to_string().as_bytes())?;
         self.headers.write(bufwrite)?;
-        if let Some(body) = &self.body {
-            body.write(bufwrite)?;
+        bufwrite.write_all(b"\r\n")?;
+        if let Some(body) = &mut self.body {
+            if self.headers.is_chunked() {
+                body.write_chunked(bufwrite, &self.trailers)?;
+            } else {
+                body.write(bufwrite)?;
+            }
         }
         Ok(())
     }
 
     pub fn new(status: Status, headers: Option<Headers>, body: Option<Body>) -> Self {
-        let mut headers = headers.unwrap_or_else(Headers::new);
-        body.as_ref().map(|b| headers.set_content_length(b.len()));
-        Message {
-            start_line: {
-                StartLine::Response {
-                    version: Version::V1_1,
-                    status: status,
-                }
+        Self::with_start_line(
+            StartLine::Response {
+                version: Version::V1_1,
+                status,
+                reason: status.reason().unwrap_or_default().to_string(),
             },
-            headers: headers,
-            body: body,
+            headers,
+            body,
+        )
+    }
+
+    pub fn new_request(
+        method: Method,
+        url: Url,
+        headers: Option<Headers>,
+        body: Option<Body>,
+    ) -> Self {
+        Self::with_start_line(
+            StartLine::Request {
+                method,
+                url,
+                version: Version::V1_1,
+            },
+            headers,
+            body,
+        )
+    }
+
+    pub(super) fn with_start_line(
+        start_line: StartLine,
+        headers: Option<Headers>,
+        body: Option<Body>,
+    ) -> Self {
+        let mut headers = headers.unwrap_or_default();
+        // Send the body chunked if its length is not known upfront
+        match body.as_ref().map(Body::len) {
+            Some(Some(length)) => {
+                // Framing the body both ways would be ambiguous
+                headers.remove("Transfer-Encoding");
+                headers.set_content_length(length)
+            }
+            Some(None) => headers.set_chunked(),
+            // A response that may have a body would otherwise be delimited by
+            // the end of the connection
+            None if matches!(start_line, StartLine::Response { .. })
+                && Self::may_have_body(&start_line)
+                && !headers.contains("Content-Length")
+                && !headers.contains("Transfer-Encoding") =>
+            {
+                headers.set_content_length(0)
+            }
+            None => (),
+        }
+        Message {
+            start_line,
+            headers,
+            body,
+            trailers: Headers::new(),
+        }
+    }
+
+    /// Return whether the connection should stay open after this message,
+    /// according to its `Connection` header and HTTP version.
+    pub fn keep_alive(&self) -> bool {
+        if self.headers.has_connection_option("close") {
+            false
+        } else if self.headers.has_connection_option("keep-alive") {
+            true
+        } else {
+            *self.version() == Version::V1_1
         }
     }
 
@@ -131,13 +454,51 @@ impl Message {
         &self.start_line
     }
 
+    pub fn start_line_mut(&mut self) -> &mut StartLine {
+        &mut self.start_line
+    }
+
+    pub fn version(&self) -> &Version {
+        match &self.start_line {
+            StartLine::Request { version, .. } => version,
+            StartLine::Response { version, .. } => version,
+        }
+    }
+
+    /// Return the status of a response, `None` for a request.
+    pub fn status(&self) -> Option<Status> {
+        match &self.start_line {
+            StartLine::Request { .. } => None,
+            StartLine::Response { status, .. } => Some(*status),
+        }
+    }
+
     pub fn headers(&self) -> &Headers {
         &self.headers
     }
 
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
     pub fn body(&self) -> &Option<Body> {
         &self.body
     }
+
+    /// Remove the body, leaving untouched the headers describing it, as in a
+    /// response to a `HEAD` request.
+    pub fn take_body(&mut self) -> Option<Body> {
+        self.body.take()
+    }
+
+    /// Return the trailer fields sent after a chunked body.
+    pub fn trailers(&self) -> &Headers {
+        &self.trailers
+    }
+
+    pub fn trailers_mut(&mut self) -> &mut Headers {
+        &mut self.trailers
+    }
 }
 
 #[cfg(test)]
@@ -150,17 +511,17 @@ mod tests {
         assert_eq!(
             StartLine::Request {
                 method: Method::Get,
-                url: String::from("index.html"),
+                url: "/index.html".parse().unwrap(),
                 version: Version::V1_1
             },
-            "GET index.html HTTP/1.1".parse().unwrap()
+            "GET /index.html HTTP/1.1".parse().unwrap()
         );
     }
 
     #[test]
     #[should_panic(expected = "find the version")]
     fn test_start_line_from_str_panic_if_missing_version() {
-        StartLine::from_str("GET index.html").unwrap();
+        StartLine::from_str("GET /index.html").unwrap();
     }
 
     #[test]
@@ -175,6 +536,121 @@ mod tests {
         StartLine::from_str("").unwrap();
     }
 
+    #[test]
+    fn test_start_line_from_str_status_line() {
+        assert_eq!(
+            StartLine::Response {
+                version: Version::V1_1,
+                status: Status::Ok,
+                reason: String::from("OK")
+            },
+            "HTTP/1.1 200 OK".parse().unwrap()
+        );
+        assert_eq!(
+            StartLine::Response {
+                version: Version::V1_0,
+                status: Status::NotFound,
+                reason: String::from("Nothing to see here")
+            },
+            "HTTP/1.0 404 Nothing to see here".parse().unwrap()
+        );
+        assert_eq!(
+            StartLine::Response {
+                version: Version::V1_1,
+                status: Status::from_code(599).unwrap(),
+                reason: String::new()
+            },
+            "HTTP/1.1 599".parse().unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse given version")]
+    fn test_start_line_from_str_panic_if_wrong_status_line_version() {
+        StartLine::from_str("HTTP/4 200 OK").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't find the status code")]
+    fn test_start_line_from_str_panic_if_missing_status_code() {
+        StartLine::from_str("HTTP/1.1").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse status code")]
+    fn test_start_line_from_str_panic_if_wrong_status_code() {
+        StartLine::from_str("HTTP/1.1 20 OK").unwrap();
+    }
+
+    #[test]
+    fn test_start_line_to_string_status_line() {
+        assert_eq!(
+            "HTTP/1.1 404 Nothing to see here\r\n",
+            StartLine::from_str("HTTP/1.1 404 Nothing to see here")
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_message_read_response() {
+        let mut bufread = BufReader::new(
+            "\
+HTTP/1.1 200 OK\r
+Content-Length: 5\r
+\r
+helloHTTP/1.1 304 Not Modified\r
+Content-Length: 5\r
+\r
+"
+            .as_bytes(),
+        );
+        let response = Message::read(&mut bufread).unwrap();
+        assert_eq!(Some(Status::Ok), response.status());
+        assert_eq!(&Some(Body::from_str("hello").unwrap()), response.body());
+        let response = Message::read(&mut bufread).unwrap();
+        assert_eq!(Some(Status::NotModified), response.status());
+        assert_eq!(&None, response.body());
+    }
+
+    #[test]
+    fn test_message_read_response_until_close() {
+        let mut bufread = BufReader::new("HTTP/1.0 200 OK\r\n\r\nhello world".as_bytes());
+        let response = Message::read(&mut bufread).unwrap();
+        assert_eq!(
+            &Some(Body::from_str("hello world").unwrap()),
+            response.body()
+        );
+    }
+
+    #[test]
+    fn test_message_read_head() {
+        let mut bufread =
+            BufReader::new("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello".as_bytes());
+        let mut response = Message::read_head(&mut bufread).unwrap();
+        assert_eq!(Some(5), response.headers().get_content_length().unwrap());
+        assert_eq!(&None, response.body());
+        response.read_body(&mut bufread).unwrap();
+        assert_eq!(&Some(Body::from_str("hello").unwrap()), response.body());
+    }
+
+    #[test]
+    fn test_message_write_request() {
+        let mut buffer = Vec::new();
+        Message::new_request(
+            Method::Post,
+            "/upload".parse().unwrap(),
+            None,
+            Some(Body::from_str("hello").unwrap()),
+        )
+        .write(&mut buffer)
+        .unwrap();
+        assert_eq!(
+            "POST /upload HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
     #[test]
     fn test_message_read_request_without_body() {
         let mut bufread = BufReader::new(
@@ -189,14 +665,15 @@ User-Agent: curl
             Message {
                 start_line: StartLine::Request {
                     method: Method::Get,
-                    url: String::from("/"),
+                    url: "/".parse().unwrap(),
                     version: Version::V1_1
                 },
                 headers: Headers::from([
                     (String::from("Content-Type"), String::from("text/plain")),
                     (String::from("User-Agent"), String::from("curl"))
                 ]),
-                body: None
+                body: None,
+                trailers: Headers::new()
             },
             Message::read(&mut bufread).unwrap()
         );
@@ -218,14 +695,15 @@ Test: Beyond empty line
             Message {
                 start_line: StartLine::Request {
                     method: Method::Get,
-                    url: String::from("/"),
+                    url: "/".parse().unwrap(),
                     version: Version::V1_1
                 },
                 headers: Headers::from([
                     (String::from("Content-Type"), String::from("text/plain")),
                     (String::from("User-Agent"), String::from("curl"))
                 ]),
-                body: None
+                body: None,
+                trailers: Headers::new()
             },
             Message::read(&mut bufread).unwrap()
         );
@@ -275,7 +753,7 @@ hello world"
             Message {
                 start_line: StartLine::Request {
                     method: Method::Get,
-                    url: String::from("/"),
+                    url: "/".parse().unwrap(),
                     version: Version::V1_1
                 },
                 headers: Headers::from([
@@ -283,15 +761,295 @@ hello world"
                     (String::from("User-Agent"), String::from("curl")),
                     (String::from("Content-Length"), String::from("11")),
                 ]),
-                body: Some(Body::from_str("hello world").unwrap())
+                body: Some(Body::from_str("hello world").unwrap()),
+                trailers: Headers::new()
             },
             Message::read(&mut bufread).unwrap()
         );
     }
 
+    #[test]
+    fn test_message_read_request_with_chunked_body() {
+        let mut bufread = BufReader::new(
+            "\
+POST / HTTP/1.1\r
+Transfer-Encoding: chunked\r
+\r
+5\r
+hello\r
+6\r
+ world\r
+0\r
+Checksum: 42\r
+\r
+GET / HTTP/1.1\r
+\r
+"
+            .as_bytes(),
+        );
+        let message = Message::read(&mut bufread).unwrap();
+        assert_eq!(
+            &Some(Body::from_str("hello world").unwrap()),
+            message.body()
+        );
+        assert_eq!(Some("42"), message.trailers().get("Checksum"));
+        // The next pipelined request is left untouched
+        assert!(Message::read(&mut bufread).unwrap().body().is_none());
+    }
+
+    #[test]
+    fn test_message_write() {
+        let mut buffer = Vec::new();
+        Message::new(Status::Ok, None, Some(Body::from_str("hello").unwrap()))
+            .write(&mut buffer)
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
+    #[test]
+    fn test_message_write_without_body() {
+        let mut buffer = Vec::new();
+        Message::new(Status::Ok, None, None)
+            .write(&mut buffer)
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+        let mut buffer = Vec::new();
+        Message::new(Status::NoContent, None, None)
+            .write(&mut buffer)
+            .unwrap();
+        assert_eq!(
+            "HTTP/1.1 204 No Content\r\n\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
+    #[test]
+    fn test_message_write_stream_of_unknown_length() {
+        let mut buffer = Vec::new();
+        Message::new(
+            Status::Ok,
+            None,
+            Some(Body::from_reader("hello".as_bytes(), None)),
+        )
+        .write(&mut buffer)
+        .unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
+    #[test]
+    fn test_message_write_chunked() {
+        let mut buffer = Vec::new();
+        let mut message = Message::new(Status::Ok, None, Some(Body::from_str("hello").unwrap()));
+        message.headers_mut().set_chunked();
+        message.trailers_mut().set("Checksum", "42");
+        message.write(&mut buffer).unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\nChecksum: 42\r\n\r\n",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
+    #[test]
+    fn test_message_keep_alive() {
+        let read = |s: &str| Message::read(&mut BufReader::new(s.as_bytes())).unwrap();
+        assert!(read("GET / HTTP/1.1\r\n\r\n").keep_alive());
+        assert!(!read("GET / HTTP/1.1\r\nConnection: close\r\n\r\n").keep_alive());
+        assert!(!read("GET / HTTP/1.0\r\n\r\n").keep_alive());
+        assert!(read("GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n").keep_alive());
+    }
+
     #[test]
     #[should_panic(expected = "parse given method")]
     fn test_start_line_from_str_panic_if_wrong_method() {
-        StartLine::from_str("GOT / HTTP/1.1").unwrap();
+        StartLine::from_str("G@T / HTTP/1.1").unwrap();
+    }
+
+    #[test]
+    fn test_start_line_from_str_unknown_method() {
+        let e = StartLine::from_str("GOT / HTTP/1.1").unwrap_err();
+        assert_eq!(Status::NotImplemented, e.status());
+        assert_eq!("not implemented method 'GOT'", e.to_string());
+    }
+
+    fn read_strict(s: &str) -> Result<Message> {
+        Message::read_with(
+            &mut BufReader::new(s.as_bytes()),
+            &ParseOptions {
+                strict: true,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_message_read_strict() {
+        let request =
+            read_strict("POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5, 5\r\n\r\nhello")
+                .unwrap();
+        assert_eq!(&Some(Body::from("hello")), request.body());
+        assert!(read_strict("GET / HTTP/1.0\r\n\r\n").is_ok());
+    }
+
+    #[test]
+    fn test_message_read_tolerates_bare_line_feeds_and_carriage_returns() {
+        let request = Message::read(&mut BufReader::new(
+            "GET / HTTP/1.1\nX: a\rb\r\n\n".as_bytes(),
+        ))
+        .unwrap();
+        assert_eq!(Some("a b"), request.headers().get("X"));
+    }
+
+    #[test]
+    #[should_panic(expected = "line doesn't end with CRLF")]
+    fn test_message_read_strict_panic_if_bare_line_feed() {
+        read_strict("GET / HTTP/1.1\r\nHost: localhost\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "line holds a bare CR")]
+    fn test_message_read_strict_panic_if_bare_carriage_return() {
+        read_strict("GET / HTTP/1.1\r\nHost: local\rhost\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid request line 'GET  / HTTP/1.1'")]
+    fn test_message_read_strict_panic_if_extra_whitespace_in_request_line() {
+        read_strict("GET  / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting Content-Length values")]
+    fn test_message_read_strict_panic_if_conflicting_content_length() {
+        read_strict(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\nContent-Length: 6\r\n\r\nhello!",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting Content-Length values")]
+    fn test_message_read_panic_if_conflicting_content_length() {
+        Message::read(&mut BufReader::new(
+            "POST / HTTP/1.1\r\nContent-Length: 5, 6\r\n\r\nhello!".as_bytes(),
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Content-Length")]
+    fn test_message_read_strict_panic_if_invalid_content_length() {
+        read_strict("POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: +5\r\n\r\nhello")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_message_read_overflowing_content_length() {
+        let smuggled = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 99999999999999999999999\r\n\r\nGET /admin HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        for result in [
+            Message::read(&mut BufReader::new(smuggled.as_bytes())),
+            read_strict(smuggled),
+        ] {
+            match result {
+                Err(Error::ContentTooLarge(reason)) => {
+                    assert!(reason.starts_with("body of 99999999999999999999999 bytes"))
+                }
+                result => panic!("expected a body too large, got {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "both Content-Length and Transfer-Encoding are present")]
+    fn test_message_read_strict_panic_if_content_length_and_transfer_encoding() {
+        read_strict(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "chunked isn't the final transfer coding")]
+    fn test_message_read_strict_panic_if_not_chunked() {
+        read_strict("POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: gzip\r\n\r\n")
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "missing Host header")]
+    fn test_message_read_strict_panic_if_missing_host() {
+        read_strict("GET / HTTP/1.1\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "multiple Host headers")]
+    fn test_message_read_strict_panic_if_multiple_hosts() {
+        read_strict("GET / HTTP/1.1\r\nHost: a\r\nHost: b\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "obsolete line folding isn't allowed")]
+    fn test_message_read_strict_panic_if_folded_line() {
+        read_strict("GET / HTTP/1.1\r\nHost: localhost\r\nX: a\r\n b\r\n\r\n").unwrap();
+    }
+
+    fn read_limited(s: &str) -> Result<Message> {
+        Message::read_with(
+            &mut BufReader::new(s.as_bytes()),
+            &ParseOptions {
+                max_start_line_length: 16,
+                max_header_size: 32,
+                max_headers: 2,
+                max_body_size: 5,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_message_read_within_limits() {
+        let request =
+            read_limited("POST /a HTTP/1.1\r\nContent-Length: 5\r\nX: 1\r\n\r\nhello").unwrap();
+        assert_eq!(&Some(Body::from("hello")), request.body());
+    }
+
+    #[test]
+    #[should_panic(expected = "line is longer than 16 bytes")]
+    fn test_message_read_panic_if_start_line_too_long() {
+        read_limited("GET /abcdefgh HTTP/1.1\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "line is longer than")]
+    fn test_message_read_panic_if_header_section_too_large() {
+        read_limited("GET / HTTP/1.1\r\nX: 0123456789abcdef\r\nY: 0123456789abcdef\r\n\r\n")
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 2 fields")]
+    fn test_message_read_panic_if_too_many_headers() {
+        read_limited("GET / HTTP/1.1\r\nX: 1\r\nY: 2\r\nZ: 3\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "body of 1000000000 bytes is larger than 5 bytes")]
+    fn test_message_read_panic_if_body_too_large() {
+        read_limited("POST / HTTP/1.1\r\nContent-Length: 1000000000\r\n\r\nhello").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "chunked body is larger than 5 bytes")]
+    fn test_message_read_panic_if_chunked_body_too_large() {
+        read_limited("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nabcd\r\n2\r\nef\r\n0\r\n\r\n")
+            .unwrap();
     }
 }
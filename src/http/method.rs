@@ -1,5 +1,5 @@
 /// HTTP methods.
-#[derive(EnumString, Display, Debug, PartialEq)]
+#[derive(EnumString, Display, Debug, Clone, Copy, PartialEq)]
 pub enum Method {
     #[strum(serialize = "HEAD")]
     Head,
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+/// MIME type sent when nothing better is known.
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// Number of bytes looked at when sniffing content.
+const SNIFF_LENGTH: usize = 512;
+
+const BUILTIN_TYPES: &[(&str, &str)] = &[
+    ("7z", "application/x-7z-compressed"),
+    ("avif", "image/avif"),
+    ("bmp", "image/bmp"),
+    ("bz2", "application/x-bzip2"),
+    ("c", "text/x-c"),
+    ("cpp", "text/x-c++"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("h", "text/x-c"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("iso", "application/x-iso9660-image"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("md", "text/markdown"),
+    ("mjs", "text/javascript"),
+    ("mkv", "video/x-matroska"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("ogg", "audio/ogg"),
+    ("otf", "font/otf"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("py", "text/x-python"),
+    ("rs", "text/x-rust"),
+    ("sh", "application/x-sh"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("toml", "application/toml"),
+    ("ttf", "font/ttf"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("wav", "audio/wav"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("xml", "application/xml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("zip", "application/zip"),
+];
+
+/// Magic numbers recognized when sniffing content, checked in order.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x00asm", "application/wasm"),
+    (b"<!DOCTYPE html", "text/html"),
+    (b"<!doctype html", "text/html"),
+    (b"<html", "text/html"),
+    (b"<?xml", "application/xml"),
+];
+
+/// Extension-based MIME type table, with an optional content sniffing fallback
+/// for files without a known extension.
+#[derive(Debug, Clone)]
+pub struct MimeTypes {
+    types: HashMap<String, String>,
+    sniffing: bool,
+}
+
+impl Default for MimeTypes {
+    fn default() -> Self {
+        Self {
+            types: BUILTIN_TYPES
+                .iter()
+                .map(|(ext, mime)| (ext.to_string(), mime.to_string()))
+                .collect(),
+            sniffing: true,
+        }
+    }
+}
+
+impl MimeTypes {
+    /// Associate `extension`, without its leading dot, to `mime_type`,
+    /// replacing any previous association.
+    pub fn insert(&mut self, extension: &str, mime_type: impl Into<String>) {
+        self.types
+            .insert(extension.to_ascii_lowercase(), mime_type.into());
+    }
+
+    pub fn set_sniffing(&mut self, sniffing: bool) {
+        self.sniffing = sniffing;
+    }
+
+    /// Return the MIME type associated to the extension of `path`.
+    pub fn from_extension(&self, path: impl AsRef<Path>) -> Option<&str> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        self.types.get(&extension).map(String::as_str)
+    }
+
+    /// Return the `Content-Type` of the file at `path`, guessed from its
+    /// extension or, if enabled, from the first bytes read from `content`.
+    ///
+    /// Text types are given a UTF-8 charset unless they already have one.
+    pub fn content_type(&self, path: impl AsRef<Path>, content: impl Read) -> String {
+        let mime_type = match self.from_extension(&path) {
+            Some(mime_type) => mime_type,
+            None if self.sniffing => {
+                let mut buffer = Vec::with_capacity(SNIFF_LENGTH);
+                match content.take(SNIFF_LENGTH as u64).read_to_end(&mut buffer) {
+                    Ok(_) => sniff(&buffer).unwrap_or(DEFAULT_MIME_TYPE),
+                    Err(_) => DEFAULT_MIME_TYPE,
+                }
+            }
+            None => DEFAULT_MIME_TYPE,
+        };
+        with_charset(mime_type)
+    }
+}
+
+/// Guess a MIME type from the first bytes of a file, using well-known magic
+/// numbers or recognizing plain text.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, mime_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+    {
+        return Some(mime_type);
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    // A multi-byte character may be cut at the end of the sniffed bytes
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    if text
+        .chars()
+        .all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r' | '\x0c'))
+    {
+        Some("text/plain")
+    } else {
+        None
+    }
+}
+
+fn with_charset(mime_type: &str) -> String {
+    let is_text = mime_type.starts_with("text/")
+        || matches!(
+            mime_type,
+            "application/json" | "application/xml" | "image/svg+xml"
+        );
+    if is_text && !mime_type.contains("charset=") {
+        format!("{}; charset=utf-8", mime_type)
+    } else {
+        mime_type.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_extension() {
+        let mime_types = MimeTypes::default();
+        assert_eq!(Some("text/html"), mime_types.from_extension("index.HTML"));
+        assert_eq!(
+            Some("application/pdf"),
+            mime_types.from_extension("/a/b.pdf")
+        );
+        assert_eq!(None, mime_types.from_extension("Makefile"));
+        assert_eq!(None, mime_types.from_extension("archive.unknown"));
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut mime_types = MimeTypes::default();
+        mime_types.insert("Unknown", "application/x-unknown");
+        mime_types.insert("txt", "text/plain; charset=latin1");
+        assert_eq!(
+            "application/x-unknown",
+            mime_types.content_type("archive.unknown", "".as_bytes())
+        );
+        assert_eq!(
+            "text/plain; charset=latin1",
+            mime_types.content_type("notes.txt", "".as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_content_type_adds_charset_to_text() {
+        let mime_types = MimeTypes::default();
+        assert_eq!(
+            "text/html; charset=utf-8",
+            mime_types.content_type("index.html", "".as_bytes())
+        );
+        assert_eq!("image/png", mime_types.content_type("a.png", "".as_bytes()));
+    }
+
+    #[test]
+    fn test_content_type_sniffs_extensionless_files() {
+        let mut mime_types = MimeTypes::default();
+        assert_eq!(
+            "text/plain; charset=utf-8",
+            mime_types.content_type("README", "héllo\n".as_bytes())
+        );
+        assert_eq!(
+            "image/png",
+            mime_types.content_type("picture", &b"\x89PNG\r\n\x1a\n\0\0"[..])
+        );
+        mime_types.set_sniffing(false);
+        assert_eq!(
+            DEFAULT_MIME_TYPE,
+            mime_types.content_type("README", "hello\n".as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(Some("text/html"), sniff(b"<!DOCTYPE html><html></html>"));
+        assert_eq!(Some("image/webp"), sniff(b"RIFF\0\0\0\0WEBPVP8 "));
+        assert_eq!(
+            Some("text/plain"),
+            sniff("caf\u{e9}".as_bytes()[..4].as_ref())
+        );
+        assert_eq!(None, sniff(b"\x7fELF\x02\x01\x01\0"));
+    }
+}
@@ -0,0 +1,163 @@
+use super::headers::{is_valid_name, split_quoted};
+use super::{Error, Result};
+use std::fmt;
+
+/// An element of an `Accept*` header along with its weight, such as
+/// `text/html;q=0.8` or `gzip;q=0`.
+///
+/// The weight is kept in thousandths, the precision allowed for qvalues, so
+/// that it can be compared exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityItem<T> {
+    pub item: T,
+    pub weight: u16,
+}
+
+impl<T> QualityItem<T> {
+    pub fn new(item: T, weight: u16) -> Self {
+        Self { item, weight }
+    }
+
+    /// Return the weight between 0 and 1.
+    pub fn quality(&self) -> f32 {
+        f32::from(self.weight) / 1000.0
+    }
+}
+
+/// Parse a qvalue, between 0 and 1 with at most three decimals, into
+/// thousandths.
+fn parse_weight(s: &str) -> Option<u16> {
+    let (units, decimals) = s.split_once('.').unwrap_or((s, ""));
+    if !matches!(units, "0" | "1")
+        || decimals.len() > 3
+        || !decimals.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let weight = format!("{}{:0<3}", units, decimals).parse().ok()?;
+    (weight <= 1000).then_some(weight)
+}
+
+/// Parse the elements of an `Accept*` header, sorted by decreasing weight
+/// while keeping the order of elements with the same weight.
+///
+/// Items are parsed with `parse_item` from the text before the `q` parameter,
+/// which defaults to 1. Parameters following it are ignored.
+pub(super) fn parse_list<'a, T>(
+    elements: impl Iterator<Item = &'a str>,
+    parse_item: impl Fn(&str) -> Result<T>,
+) -> Result<Vec<QualityItem<T>>> {
+    let mut items = elements
+        .map(|element| {
+            let malformed =
+                || Error::MalformedHeaders(format!("invalid quality value in '{}'", element));
+            let parts = split_quoted(element, ';');
+            let q = parts.iter().position(|part| {
+                part.trim_matches([' ', '\t'])
+                    .split_once('=')
+                    .is_some_and(|(name, _)| name.eq_ignore_ascii_case("q"))
+            });
+            let weight = match q {
+                Some(q) => {
+                    let (_, value) = parts[q].split_once('=').ok_or_else(malformed)?;
+                    parse_weight(value.trim_matches([' ', '\t'])).ok_or_else(malformed)?
+                }
+                None => 1000,
+            };
+            let end = q.map_or(element.len(), |q| {
+                parts[..q]
+                    .iter()
+                    .map(|part| part.len() + 1)
+                    .sum::<usize>()
+                    .saturating_sub(1)
+            });
+            Ok(QualityItem::new(parse_item(&element[..end])?, weight))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    items.sort_by_key(|item| std::cmp::Reverse(item.weight));
+    Ok(items)
+}
+
+/// Parse an `Accept-Charset`, `Accept-Encoding` or `Accept-Language` element,
+/// which is a token compared case-insensitively.
+pub(super) fn parse_token(s: &str) -> Result<String> {
+    let s = s.trim_matches([' ', '\t']);
+    if !is_valid_name(s) {
+        return Err(Error::MalformedHeaders(format!("invalid token '{}'", s)));
+    }
+    Ok(s.to_ascii_lowercase())
+}
+
+impl<T: fmt::Display> fmt::Display for QualityItem<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.item)?;
+        match self.weight {
+            1000.. => (),
+            0 => write!(f, ";q=0")?,
+            weight => {
+                let decimals = format!("{:03}", weight);
+                write!(f, ";q=0.{}", decimals.trim_end_matches('0'))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::MediaType;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_weight() {
+        assert_eq!(Some(1000), parse_weight("1"));
+        assert_eq!(Some(1000), parse_weight("1.000"));
+        assert_eq!(Some(500), parse_weight("0.5"));
+        assert_eq!(Some(5), parse_weight("0.005"));
+        assert_eq!(Some(0), parse_weight("0"));
+        assert_eq!(None, parse_weight("1.5"));
+        assert_eq!(None, parse_weight("0.0001"));
+        assert_eq!(None, parse_weight("-1"));
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let items = parse_list(
+            [
+                "text/html;level=1",
+                "text/*;q=0.3",
+                "*/*; q=0.1",
+                "text/plain; Q=0.5;ext=1",
+            ]
+            .into_iter(),
+            MediaType::from_str,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "text/html; level=1",
+                "text/plain;q=0.5",
+                "text/*;q=0.3",
+                "*/*;q=0.1"
+            ],
+            items.iter().map(|i| i.to_string()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_list_keeps_order_of_same_weight() {
+        let items = parse_list(["gzip", "br;q=0.9", "identity"].into_iter(), parse_token).unwrap();
+        assert_eq!(
+            vec!["gzip", "identity", "br"],
+            items.iter().map(|i| i.item.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(0.9, items[2].quality());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid quality value in 'gzip;q=2'")]
+    fn test_parse_list_panic_if_invalid_quality() {
+        parse_list(["gzip;q=2"].into_iter(), parse_token).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Error, Result};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Byte range as requested in a `Range` header, before being resolved against
+/// the length of the representation.
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    /// `first-last`, both inclusive
+    FromTo(usize, usize),
+    /// `first-`, up to the end
+    From(usize),
+    /// `-length`, the last bytes
+    Suffix(usize),
+}
+
+impl ByteRange {
+    /// Resolve the range against a representation of `length` bytes, returning
+    /// `None` if it is unsatisfiable.
+    pub fn resolve(&self, length: usize) -> Option<Range<usize>> {
+        let range = match *self {
+            ByteRange::FromTo(first, last) => first..length.min(last.saturating_add(1)),
+            ByteRange::From(first) => first..length,
+            ByteRange::Suffix(suffix) => length.saturating_sub(suffix)..length,
+        };
+        if range.is_empty() {
+            None
+        } else {
+            Some(range)
+        }
+    }
+}
+
+impl FromStr for ByteRange {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |n: &str| {
+            n.parse::<usize>().map_err(|e| {
+                Error::MalformedHeaders(format!("couldn't parse range '{}': {}", s, e))
+            })
+        };
+        let (first, last) = s.trim().split_once('-').ok_or_else(|| {
+            Error::MalformedHeaders(format!("couldn't find '-' in range '{}'", s))
+        })?;
+        match (first, last) {
+            ("", suffix) => Ok(ByteRange::Suffix(parse(suffix)?)),
+            (first, "") => Ok(ByteRange::From(parse(first)?)),
+            (first, last) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if last < first {
+                    return Err(Error::MalformedHeaders(format!(
+                        "range '{}' ends before it starts",
+                        s
+                    )));
+                }
+                Ok(ByteRange::FromTo(first, last))
+            }
+        }
+    }
+}
+
+/// Set of byte ranges from a `Range` header, such as `bytes=0-99,200-`.
+#[derive(Debug, PartialEq)]
+pub struct Ranges(Vec<ByteRange>);
+
+impl Ranges {
+    /// Resolve every range against a representation of `length` bytes, leaving
+    /// the unsatisfiable ones aside. The ranges are returned in ascending
+    /// order, the ones overlapping or adjacent to each other being merged, so
+    /// that no byte is sent twice.
+    pub fn resolve(&self, length: usize) -> Vec<Range<usize>> {
+        let mut ranges: Vec<_> = self.0.iter().filter_map(|r| r.resolve(length)).collect();
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+impl FromStr for Ranges {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ranges = s
+            .trim()
+            .strip_prefix("bytes=")
+            .ok_or_else(|| Error::MalformedHeaders(format!("unsupported range unit in '{}'", s)))?
+            .split(',')
+            .filter(|r| !r.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if ranges.is_empty() {
+            return Err(Error::MalformedHeaders(format!(
+                "couldn't find any range in '{}'",
+                s
+            )));
+        }
+        Ok(Ranges(ranges))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_from_str() {
+        assert_eq!(
+            Ranges(vec![
+                ByteRange::FromTo(0, 499),
+                ByteRange::From(9500),
+                ByteRange::Suffix(200)
+            ]),
+            "bytes=0-499, 9500-,-200".parse().unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported range unit")]
+    fn test_ranges_from_str_panic_if_unit_is_not_bytes() {
+        Ranges::from_str("lines=0-10").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "ends before it starts")]
+    fn test_ranges_from_str_panic_if_range_is_reversed() {
+        Ranges::from_str("bytes=10-0").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't find any range")]
+    fn test_ranges_from_str_panic_if_empty() {
+        Ranges::from_str("bytes=").unwrap();
+    }
+
+    #[test]
+    fn test_ranges_resolve() {
+        let ranges: Ranges = "bytes=9500-,0-499,20000-,-200".parse().unwrap();
+        assert_eq!(vec![0..500, 9500..10000], ranges.resolve(10000));
+    }
+
+    #[test]
+    fn test_ranges_resolve_merges_overlapping_ranges() {
+        let ranges: Ranges = "bytes=100-20000,0-499,600-699,500-549".parse().unwrap();
+        assert_eq!(vec![0..10000], ranges.resolve(10000));
+        let ranges: Ranges = "bytes=0-9,0-9,20-29,5-14".parse().unwrap();
+        assert_eq!(vec![0..15, 20..30], ranges.resolve(100));
+    }
+
+    #[test]
+    fn test_ranges_resolve_suffix_longer_than_length() {
+        let ranges: Ranges = "bytes=-200".parse().unwrap();
+        assert_eq!(vec![0..100], ranges.resolve(100));
+        assert!(ranges.resolve(0).is_empty());
+    }
+}
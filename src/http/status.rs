@@ -1,10 +1,235 @@
+use super::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(EnumString, Display, Debug, PartialEq)]
-pub enum Status {
-    #[strum(serialize = "200 OK")]
-    Ok = 200,
-    #[strum(serialize = "202 Accepted")]
-    Accepted = 202,
-    #[strum(serialize = "404 Not Found")]
-    NotFound = 404,
+/// Status code missing from the registry, between 100 and 999.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnregisteredCode(u16);
+
+macro_rules! statuses {
+    ($($variant:ident = $code:literal, $reason:literal;)*) => {
+        /// HTTP status codes, covering the IANA registry.
+        ///
+        /// Codes missing from the registry are represented by `Status::Other`,
+        /// which only `Status::from_code` creates so that a registered code
+        /// always maps to its own variant.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Status {
+            $($variant,)*
+            /// Unregistered status code, without reason phrase
+            Other(UnregisteredCode),
+        }
+
+        impl Status {
+            pub fn code(&self) -> u16 {
+                match self {
+                    $(Status::$variant => $code,)*
+                    Status::Other(UnregisteredCode(code)) => *code,
+                }
+            }
+
+            /// Return the canonical reason phrase, if the code is registered.
+            pub fn reason(&self) -> Option<&'static str> {
+                match self {
+                    $(Status::$variant => Some($reason),)*
+                    Status::Other(_) => None,
+                }
+            }
+
+            /// Return the status of `code`, which must have three digits.
+            pub fn from_code(code: u16) -> Result<Self> {
+                match code {
+                    $($code => Ok(Status::$variant),)*
+                    100..=999 => Ok(Status::Other(UnregisteredCode(code))),
+                    _ => Err(Error::MalformedStatusLine(format!(
+                        "status code {} doesn't have three digits",
+                        code
+                    ))),
+                }
+            }
+        }
+    };
+}
+
+statuses! {
+    Continue = 100, "Continue";
+    SwitchingProtocols = 101, "Switching Protocols";
+    Processing = 102, "Processing";
+    EarlyHints = 103, "Early Hints";
+    Ok = 200, "OK";
+    Created = 201, "Created";
+    Accepted = 202, "Accepted";
+    NonAuthoritativeInformation = 203, "Non-Authoritative Information";
+    NoContent = 204, "No Content";
+    ResetContent = 205, "Reset Content";
+    PartialContent = 206, "Partial Content";
+    MultiStatus = 207, "Multi-Status";
+    AlreadyReported = 208, "Already Reported";
+    ImUsed = 226, "IM Used";
+    MultipleChoices = 300, "Multiple Choices";
+    MovedPermanently = 301, "Moved Permanently";
+    Found = 302, "Found";
+    SeeOther = 303, "See Other";
+    NotModified = 304, "Not Modified";
+    UseProxy = 305, "Use Proxy";
+    TemporaryRedirect = 307, "Temporary Redirect";
+    PermanentRedirect = 308, "Permanent Redirect";
+    BadRequest = 400, "Bad Request";
+    Unauthorized = 401, "Unauthorized";
+    PaymentRequired = 402, "Payment Required";
+    Forbidden = 403, "Forbidden";
+    NotFound = 404, "Not Found";
+    MethodNotAllowed = 405, "Method Not Allowed";
+    NotAcceptable = 406, "Not Acceptable";
+    ProxyAuthenticationRequired = 407, "Proxy Authentication Required";
+    RequestTimeout = 408, "Request Timeout";
+    Conflict = 409, "Conflict";
+    Gone = 410, "Gone";
+    LengthRequired = 411, "Length Required";
+    PreconditionFailed = 412, "Precondition Failed";
+    ContentTooLarge = 413, "Content Too Large";
+    UriTooLong = 414, "URI Too Long";
+    UnsupportedMediaType = 415, "Unsupported Media Type";
+    RangeNotSatisfiable = 416, "Range Not Satisfiable";
+    ExpectationFailed = 417, "Expectation Failed";
+    MisdirectedRequest = 421, "Misdirected Request";
+    UnprocessableContent = 422, "Unprocessable Content";
+    Locked = 423, "Locked";
+    FailedDependency = 424, "Failed Dependency";
+    TooEarly = 425, "Too Early";
+    UpgradeRequired = 426, "Upgrade Required";
+    PreconditionRequired = 428, "Precondition Required";
+    TooManyRequests = 429, "Too Many Requests";
+    RequestHeaderFieldsTooLarge = 431, "Request Header Fields Too Large";
+    UnavailableForLegalReasons = 451, "Unavailable For Legal Reasons";
+    InternalServerError = 500, "Internal Server Error";
+    NotImplemented = 501, "Not Implemented";
+    BadGateway = 502, "Bad Gateway";
+    ServiceUnavailable = 503, "Service Unavailable";
+    GatewayTimeout = 504, "Gateway Timeout";
+    HttpVersionNotSupported = 505, "HTTP Version Not Supported";
+    VariantAlsoNegotiates = 506, "Variant Also Negotiates";
+    InsufficientStorage = 507, "Insufficient Storage";
+    LoopDetected = 508, "Loop Detected";
+    NotExtended = 510, "Not Extended";
+    NetworkAuthenticationRequired = 511, "Network Authentication Required";
+}
+
+impl Status {
+    /// 1xx: the request was received, continuing process
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.code())
+    }
+
+    /// 2xx: the request was successfully received, understood, and accepted
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.code())
+    }
+
+    /// 3xx: further action needs to be taken to complete the request
+    pub fn is_redirection(&self) -> bool {
+        (300..400).contains(&self.code())
+    }
+
+    /// 4xx: the request contains bad syntax or cannot be fulfilled
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.code())
+    }
+
+    /// 5xx: the server failed to fulfill an apparently valid request
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.code())
+    }
+}
+
+/// Display the status code followed by its reason phrase, as in a status-line.
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason() {
+            Some(reason) => write!(f, "{} {}", self.code(), reason),
+            None => write!(f, "{}", self.code()),
+        }
+    }
+}
+
+/// Parse a status code optionally followed by a reason phrase, which is
+/// ignored.
+impl FromStr for Status {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.split(' ').next().unwrap_or_default();
+        if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::MalformedStatusLine(format!(
+                "couldn't parse status code '{}'",
+                code
+            )));
+        }
+        Status::from_code(code.parse().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_and_reason() {
+        assert_eq!(200, Status::Ok.code());
+        assert_eq!(Some("OK"), Status::Ok.reason());
+        assert_eq!(413, Status::ContentTooLarge.code());
+        assert_eq!(Some("Content Too Large"), Status::ContentTooLarge.reason());
+        assert_eq!(599, Status::from_code(599).unwrap().code());
+        assert_eq!(None, Status::from_code(599).unwrap().reason());
+    }
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(Status::NotFound, Status::from_code(404).unwrap());
+        assert_eq!(Status::Ok, Status::from_code(200).unwrap());
+        assert!(matches!(Status::from_code(418).unwrap(), Status::Other(_)));
+        for code in 100..=999 {
+            assert_eq!(code, Status::from_code(code).unwrap().code());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't have three digits")]
+    fn test_from_code_panic_if_out_of_range() {
+        Status::from_code(1000).unwrap();
+    }
+
+    #[test]
+    fn test_to_string() {
+        assert_eq!("200 OK", Status::Ok.to_string());
+        assert_eq!("404 Not Found", Status::NotFound.to_string());
+        assert_eq!(
+            "505 HTTP Version Not Supported",
+            Status::HttpVersionNotSupported.to_string()
+        );
+        assert_eq!("599", Status::from_code(599).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Status::Ok, "200 OK".parse().unwrap());
+        assert_eq!(Status::Ok, "200 Fine".parse().unwrap());
+        assert_eq!(Status::from_code(299).unwrap(), "299".parse().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse status code")]
+    fn test_from_str_panic_if_not_a_number() {
+        Status::from_str("OK").unwrap();
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(Status::Continue.is_informational());
+        assert!(Status::NoContent.is_success());
+        assert!(Status::from_code(299).unwrap().is_success());
+        assert!(Status::PermanentRedirect.is_redirection());
+        assert!(Status::UriTooLong.is_client_error());
+        assert!(Status::NetworkAuthenticationRequired.is_server_error());
+        assert!(!Status::from_code(600).unwrap().is_server_error());
+    }
 }
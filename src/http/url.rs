@@ -0,0 +1,483 @@
+use super::{Error, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Form of a request-target, as defined in RFC 9112 section 3.2.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Form {
+    /// `/path?query`, used for most requests
+    Origin,
+    /// `http://host:port/path?query`, used for requests to proxies
+    Absolute,
+    /// `host:port`, used for CONNECT requests
+    Authority,
+    /// `*`, used for server-wide OPTIONS requests
+    Asterisk,
+}
+
+/// Request-target of a request, whose path and query are kept percent-encoded
+/// and decoded on demand.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    form: Form,
+    scheme: Option<String>,
+    authority: Option<String>,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+impl Url {
+    pub fn form(&self) -> Form {
+        self.form
+    }
+
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// Return the percent-encoded path, empty for the authority and asterisk
+    /// forms.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return the percent-encoded query, without its leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Return the fragment, which clients are not supposed to send but is kept
+    /// apart from the path and query when they do.
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    /// Return the origin-form of the url, made of its path and query only, as
+    /// sent to an origin server.
+    pub fn origin_form(&self) -> Url {
+        Url {
+            form: Form::Origin,
+            scheme: None,
+            authority: None,
+            path: if self.path.is_empty() {
+                String::from("/")
+            } else {
+                self.path.clone()
+            },
+            query: self.query.clone(),
+            fragment: None,
+        }
+    }
+
+    /// Return the percent-decoded segments of the path, which are not
+    /// guaranteed to be valid UTF-8.
+    pub fn segments(&self) -> Vec<Vec<u8>> {
+        self.path
+            .split('/')
+            .skip(1)
+            .map(|segment| percent_decode(segment, false))
+            .collect()
+    }
+
+    /// Return the percent-decoded query parameters, with `+` decoded as a
+    /// space as in HTML forms.
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        self.query
+            .iter()
+            .flat_map(|query| query.split('&'))
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let decode = |s| String::from_utf8_lossy(&percent_decode(s, true)).to_string();
+                (decode(name), decode(value))
+            })
+            .collect()
+    }
+
+    /// Return the value of the first query parameter named `name`.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query_pairs()
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Return the path as an absolute file system path made of the decoded
+    /// segments.
+    ///
+    /// Segments holding an encoded `/` or NUL byte are refused, as they can't
+    /// be represented as a single path component.
+    pub fn to_path(&self) -> Result<PathBuf> {
+        if !self.path.starts_with('/') {
+            return Err(Error::MalformedRequestLine(format!(
+                "couldn't map url '{}' to a path",
+                self
+            )));
+        }
+        let mut path = PathBuf::from("/");
+        for segment in self.segments() {
+            if segment.contains(&b'/') || segment.contains(&0) {
+                return Err(Error::MalformedRequestLine(format!(
+                    "url '{}' holds an invalid path segment",
+                    self
+                )));
+            }
+            path.push(path_component(segment));
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(unix)]
+fn path_component(segment: Vec<u8>) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(OsString::from_vec(segment))
+}
+
+#[cfg(not(unix))]
+fn path_component(segment: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&segment).to_string())
+}
+
+#[cfg(unix)]
+fn component_bytes(component: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    component.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn component_bytes(component: &std::ffi::OsStr) -> Vec<u8> {
+    component.to_string_lossy().as_bytes().to_vec()
+}
+
+/// Percent-encode every byte of `segment` but the unreserved characters, so
+/// that it can be used as a single path segment.
+pub fn percent_encode(segment: &[u8]) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for &b in segment {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+/// Percent-encode each component of a relative file system path, joined with
+/// `/`, which is the reverse operation of `Url::to_path`.
+pub fn encode_path(path: impl AsRef<Path>) -> String {
+    path.as_ref()
+        .iter()
+        .map(|component| percent_encode(&component_bytes(component)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Decode `%XX` sequences of `s`, and `+` as a space if `plus_as_space`.
+///
+/// Invalid sequences are kept as is.
+pub fn percent_decode(s: &str, plus_as_space: bool) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() && is_hex_pair(&bytes[i + 1..i + 3]) => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+                decoded.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+                i += 3;
+                continue;
+            }
+            b'+' if plus_as_space => decoded.push(b' '),
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    decoded
+}
+
+fn is_hex_pair(bytes: &[u8]) -> bool {
+    bytes.len() == 2 && bytes.iter().all(u8::is_ascii_hexdigit)
+}
+
+/// Check that every `%` of `s` starts a valid percent-encoded byte and that
+/// `s` has no character forbidden in a request-target.
+fn validate(s: &str, part: &str) -> Result<()> {
+    let bytes = s.as_bytes();
+    for (i, b) in bytes.iter().enumerate() {
+        let valid = match b {
+            b'%' => bytes.len() >= i + 3 && is_hex_pair(&bytes[i + 1..i + 3]),
+            b'"' | b'<' | b'>' | b'\\' | b'^' | b'`' | b'{' | b'|' | b'}' => false,
+            b => b.is_ascii_graphic(),
+        };
+        if !valid {
+            return Err(Error::MalformedRequestLine(format!(
+                "invalid character in {} '{}'",
+                part, s
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Split `s` into its path, query and fragment parts.
+fn split_path(s: &str) -> Result<(String, Option<String>, Option<String>)> {
+    let (s, fragment) = match s.split_once('#') {
+        Some((s, fragment)) => (s, Some(fragment.to_string())),
+        None => (s, None),
+    };
+    let (path, query) = match s.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (s, None),
+    };
+    validate(path, "path")?;
+    if let Some(query) = &query {
+        validate(query, "query")?;
+    }
+    Ok((path.to_string(), query, fragment))
+}
+
+impl FromStr for Url {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let url = |form, scheme, authority, (path, query, fragment)| Url {
+            form,
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        };
+        if s == "*" {
+            Ok(url(Form::Asterisk, None, None, (String::new(), None, None)))
+        } else if s.starts_with('/') {
+            Ok(url(Form::Origin, None, None, split_path(s)?))
+        } else if let Some((scheme, rest)) = s.split_once("://") {
+            if !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                || !scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            {
+                return Err(Error::MalformedRequestLine(format!(
+                    "invalid scheme in url '{}'",
+                    s
+                )));
+            }
+            let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            let (authority, rest) = rest.split_at(end);
+            validate(authority, "authority")?;
+            let (mut path, query, fragment) = split_path(rest)?;
+            if path.is_empty() {
+                path.push('/');
+            }
+            Ok(url(
+                Form::Absolute,
+                Some(scheme.to_ascii_lowercase()),
+                Some(authority.to_string()),
+                (path, query, fragment),
+            ))
+        } else if s.contains(':') && !s.contains(['/', '?', '#']) {
+            validate(s, "authority")?;
+            Ok(url(
+                Form::Authority,
+                None,
+                Some(s.to_string()),
+                (String::new(), None, None),
+            ))
+        } else {
+            Err(Error::MalformedRequestLine(format!(
+                "couldn't parse url '{}'",
+                s
+            )))
+        }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.form {
+            Form::Asterisk => return write!(f, "*"),
+            Form::Authority => return write!(f, "{}", self.authority.as_deref().unwrap_or("")),
+            Form::Absolute => write!(
+                f,
+                "{}://{}",
+                self.scheme.as_deref().unwrap_or(""),
+                self.authority.as_deref().unwrap_or("")
+            )?,
+            Form::Origin => (),
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = &self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str_origin_form() {
+        let url: Url = "/my%20dir/file.txt?sort=name&order=desc#top"
+            .parse()
+            .unwrap();
+        assert_eq!(Form::Origin, url.form());
+        assert_eq!(None, url.authority());
+        assert_eq!("/my%20dir/file.txt", url.path());
+        assert_eq!(Some("sort=name&order=desc"), url.query());
+        assert_eq!(Some("top"), url.fragment());
+    }
+
+    #[test]
+    fn test_from_str_absolute_form() {
+        let url: Url = "HTTP://example.com:8080/a/b?c=d".parse().unwrap();
+        assert_eq!(Form::Absolute, url.form());
+        assert_eq!(Some("http"), url.scheme());
+        assert_eq!(Some("example.com:8080"), url.authority());
+        assert_eq!("/a/b", url.path());
+        assert_eq!(Some("c=d"), url.query());
+        let url: Url = "http://example.com?c=d".parse().unwrap();
+        assert_eq!("/", url.path());
+    }
+
+    #[test]
+    fn test_from_str_authority_form() {
+        let url: Url = "example.com:443".parse().unwrap();
+        assert_eq!(Form::Authority, url.form());
+        assert_eq!(Some("example.com:443"), url.authority());
+        assert_eq!("", url.path());
+    }
+
+    #[test]
+    fn test_from_str_asterisk_form() {
+        assert_eq!(Form::Asterisk, Url::from_str("*").unwrap().form());
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't parse url")]
+    fn test_from_str_panic_if_relative() {
+        Url::from_str("index.html").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid character in path")]
+    fn test_from_str_panic_if_invalid_percent_encoding() {
+        Url::from_str("/100%").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid character in query")]
+    fn test_from_str_panic_if_invalid_character() {
+        Url::from_str("/?a=<b>").unwrap();
+    }
+
+    #[test]
+    fn test_origin_form() {
+        let url: Url = "http://example.com/a?b#c".parse().unwrap();
+        assert_eq!("/a?b", url.origin_form().to_string());
+        assert_eq!(Form::Origin, url.origin_form().form());
+    }
+
+    #[test]
+    fn test_to_string() {
+        for s in [
+            "*",
+            "example.com:443",
+            "http://example.com/a?b#c",
+            "/my%20file.txt?a=b",
+        ] {
+            assert_eq!(s, Url::from_str(s).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn test_segments() {
+        let url: Url = "/my%20dir/caf%C3%A9/%FF".parse().unwrap();
+        assert_eq!(
+            vec![b"my dir".to_vec(), "café".as_bytes().to_vec(), vec![0xff]],
+            url.segments()
+        );
+    }
+
+    #[test]
+    fn test_query_pairs() {
+        let url: Url = "/?sort=name&q=hello+world%21&flag&&sort=size"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            vec![
+                (String::from("sort"), String::from("name")),
+                (String::from("q"), String::from("hello world!")),
+                (String::from("flag"), String::new()),
+                (String::from("sort"), String::from("size")),
+            ],
+            url.query_pairs()
+        );
+        assert_eq!(Some(String::from("name")), url.query_param("sort"));
+        assert_eq!(None, url.query_param("order"));
+    }
+
+    #[test]
+    fn test_to_path() {
+        let url: Url = "/my%20dir/file.txt?sort=name".parse().unwrap();
+        assert_eq!(PathBuf::from("/my dir/file.txt"), url.to_path().unwrap());
+        let url: Url = "/".parse().unwrap();
+        assert_eq!(PathBuf::from("/"), url.to_path().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid path segment")]
+    fn test_to_path_panic_if_encoded_slash() {
+        Url::from_str("/a%2F..%2F..%2Fetc/passwd")
+            .unwrap()
+            .to_path()
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "couldn't map url")]
+    fn test_to_path_panic_if_asterisk() {
+        Url::from_str("*").unwrap().to_path().unwrap();
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!("a%20b%23%3F%25%2F~.txt", percent_encode(b"a b#?%/~.txt"));
+        assert_eq!("caf%C3%A9%FF", percent_encode(b"caf\xc3\xa9\xff"));
+    }
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!("my%20dir/a%23b", encode_path("my dir/a#b"));
+        assert_eq!("", encode_path(""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_encode_path_round_trip_non_utf8() {
+        let path = Path::new("dir").join(path_component(b"\xff name".to_vec()));
+        let url: Url = format!("/{}", encode_path(&path)).parse().unwrap();
+        assert_eq!(Path::new("/").join(path), url.to_path().unwrap());
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(b"a b+c".to_vec(), percent_decode("a%20b+c", false));
+        assert_eq!(b"a b c".to_vec(), percent_decode("a%20b+c", true));
+        assert_eq!(b"100%".to_vec(), percent_decode("100%", false));
+        assert_eq!(b"%zz".to_vec(), percent_decode("%zz", false));
+    }
+}
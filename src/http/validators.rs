@@ -0,0 +1,148 @@
+use super::{date, Headers};
+use std::fs::Metadata;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Validators of a representation, used to answer conditional requests.
+#[derive(Debug, PartialEq)]
+pub struct Validators {
+    /// Strong entity tag, including its double quotes
+    pub etag: String,
+    /// Modification time, truncated to the second as in HTTP-dates
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// Derive validators from the size, modification time and inode of a file
+    /// or directory.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Self {
+            etag: format!(
+                "\"{:x}-{:x}-{:x}\"",
+                inode,
+                metadata.len(),
+                modified.map(|d| d.as_nanos()).unwrap_or_default()
+            ),
+            last_modified: modified.map(|d| UNIX_EPOCH + Duration::from_secs(d.as_secs())),
+        }
+    }
+
+    /// Add `ETag` and `Last-Modified` headers.
+    pub fn set(&self, headers: &mut Headers) {
+        headers.set("ETag", self.etag.as_str());
+        if let Some(last_modified) = self.last_modified {
+            headers.set_last_modified(last_modified);
+        }
+    }
+
+    /// Return whether a GET or HEAD request can be answered with
+    /// `304 Not Modified`, according to its `If-None-Match` or, failing that,
+    /// its `If-Modified-Since` header.
+    pub fn not_modified(&self, request_headers: &Headers) -> bool {
+        if request_headers.contains("If-None-Match") {
+            return request_headers
+                .get_list("If-None-Match")
+                .into_iter()
+                .any(|tag| tag == "*" || opaque_tag(tag) == opaque_tag(&self.etag));
+        }
+        match (
+            request_headers
+                .get("If-Modified-Since")
+                .and_then(|d| date::parse(d).ok()),
+            self.last_modified,
+        ) {
+            (Some(since), Some(last_modified)) => last_modified <= since,
+            _ => false,
+        }
+    }
+
+    /// Return whether the `If-Range` condition `value` holds, which requires a
+    /// strong match with either the entity tag or the modification date.
+    pub fn if_range_matches(&self, value: &str) -> bool {
+        let value = value.trim();
+        if value.starts_with('"') {
+            value == self.etag
+        } else if value.starts_with("W/") {
+            false
+        } else {
+            self.last_modified
+                .is_some_and(|last_modified| date::format(last_modified) == value)
+        }
+    }
+}
+
+/// Return the opaque part of an entity tag, as weak comparison ignores the
+/// weakness indicator.
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATE_EXAMPLE: &str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+    fn setup_validators() -> Validators {
+        Validators {
+            etag: String::from("\"abc\""),
+            last_modified: Some(date::parse(DATE_EXAMPLE).unwrap()),
+        }
+    }
+
+    fn setup_headers(name: &str, value: &str) -> Headers {
+        Headers::from([(String::from(name), String::from(value))])
+    }
+
+    #[test]
+    fn test_set() {
+        let mut headers = Headers::new();
+        setup_validators().set(&mut headers);
+        assert_eq!(Some("\"abc\""), headers.get("ETag"));
+        assert_eq!(Some(DATE_EXAMPLE), headers.get("Last-Modified"));
+    }
+
+    #[test]
+    fn test_not_modified_if_none_match() {
+        let validators = setup_validators();
+        assert!(validators.not_modified(&setup_headers("If-None-Match", "\"xyz\", W/\"abc\"")));
+        assert!(validators.not_modified(&setup_headers("If-None-Match", "*")));
+        assert!(!validators.not_modified(&setup_headers("If-None-Match", "\"xyz\"")));
+    }
+
+    #[test]
+    fn test_not_modified_if_modified_since() {
+        let validators = setup_validators();
+        assert!(validators.not_modified(&setup_headers("If-Modified-Since", DATE_EXAMPLE)));
+        assert!(!validators.not_modified(&setup_headers(
+            "If-Modified-Since",
+            "Sat, 05 Nov 1994 08:49:37 GMT"
+        )));
+        assert!(!validators.not_modified(&setup_headers("If-Modified-Since", "yesterday")));
+        assert!(!validators.not_modified(&Headers::new()));
+    }
+
+    #[test]
+    fn test_not_modified_ignores_if_modified_since_when_if_none_match_is_present() {
+        let mut headers = setup_headers("If-None-Match", "\"xyz\"");
+        headers.set("If-Modified-Since", DATE_EXAMPLE);
+        assert!(!setup_validators().not_modified(&headers));
+    }
+
+    #[test]
+    fn test_if_range_matches() {
+        let validators = setup_validators();
+        assert!(validators.if_range_matches("\"abc\""));
+        assert!(!validators.if_range_matches("W/\"abc\""));
+        assert!(!validators.if_range_matches("\"xyz\""));
+        assert!(validators.if_range_matches(DATE_EXAMPLE));
+        assert!(!validators.if_range_matches("Sat, 05 Nov 1994 08:49:37 GMT"));
+    }
+}
@@ -2,4 +2,9 @@ extern crate strum;
 #[macro_use]
 extern crate strum_macros;
 
+#[macro_use]
+pub mod log;
+
+pub mod client;
 pub mod http;
+pub mod server;
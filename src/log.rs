@@ -0,0 +1,59 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Severity of a log message, from the most to the least severe.
+#[derive(EnumString, Display, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[strum(serialize_all = "lowercase")]
+pub enum Level {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// Set the least severe level of the messages logged from now on.
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Write a message to the standard error if `level` is enabled. The `log!`
+/// macro is more convenient to call.
+pub fn write(level: Level, args: fmt::Arguments) {
+    if enabled(level) {
+        eprintln!("[{}] {}", level, args);
+    }
+}
+
+/// Log a message formatted like with `format!`, at the given level.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log::write($level, format_args!($($arg)+))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_from_str() {
+        assert_eq!(Level::Warn, "warn".parse().unwrap());
+        assert_eq!("debug", Level::Debug.to_string());
+        assert!("verbose".parse::<Level>().is_err());
+    }
+
+    #[test]
+    fn test_enabled() {
+        assert!(enabled(Level::Error));
+        assert!(!enabled(Level::Off));
+        assert!(Level::Error < Level::Debug);
+    }
+}
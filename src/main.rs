@@ -1,39 +1,266 @@
-use std::{
-    io::{BufReader, BufWriter},
-    net::{TcpListener, TcpStream},
-};
+#[macro_use]
+extern crate webserver;
 
-use webserver::http::{index, Message, Method, Result, StartLine};
+mod cli;
+mod config;
+#[cfg(unix)]
+mod signal;
+
+use cli::{Args, Command};
+use config::{Config, Location, Site};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::{env, fs, process};
+use webserver::http::url::Form;
+use webserver::http::{index, Body, Error, Message, Method, Result, StartLine, Status};
+use webserver::log::{self, Level};
+use webserver::server::{self, connection, Server, DEFAULT_QUEUE_SIZE_PER_WORKER};
 
 fn main() {
-    let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(Command::Serve(args)) => args,
+        Ok(Command::Help) => {
+            print!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    let config = match &args.config {
+        Some(path) => Config::load(path),
+        None => Config::from_args(&args),
+    };
+    let config = config.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    });
+    log::set_level(config.log_level);
+    if let Err(e) = serve(config, args.config) {
+        log!(Level::Error, "{}", e);
+        process::exit(1);
+    }
+}
+
+fn serve(config: Config, path: Option<PathBuf>) -> Result<()> {
+    let mut server_config = server::Config::default();
+    if let Some(workers) = config.workers {
+        server_config.workers = workers;
+        server_config.queue_size = workers * DEFAULT_QUEUE_SIZE_PER_WORKER;
+    }
+    let server = Server::bind(&config.listen[..], server_config)?;
+    for address in server.local_addrs()? {
+        log!(Level::Info, "listening on http://{}", address);
+    }
+    for site in &config.sites {
+        let hosts = if site.hosts.is_empty() {
+            String::from("any host")
+        } else {
+            site.hosts.join(", ")
+        };
+        log!(Level::Info, "serving {} for {}", site.root.display(), hosts);
+    }
+    // Requests are served with the configuration current when they arrive,
+    // which a reload replaces
+    let config = Arc::new(RwLock::new(Arc::new(config)));
+    #[cfg(unix)]
+    watch_signals(server.stopper()?, Arc::clone(&config), path)?;
+    #[cfg(not(unix))]
+    let _ = path;
+    let served = server.run(move |request| {
+        let config = Arc::clone(&config.read().unwrap_or_else(PoisonError::into_inner));
+        handle_request(&config, request)
+    });
+    if !served {
+        log!(
+            Level::Warn,
+            "closing the connections left after the timeout"
+        );
+        // Dropping the server would wait for them
+        process::exit(0);
+    }
+    log!(Level::Info, "stopped");
+    Ok(())
+}
 
-    for stream in listener.incoming() {
-        let stream = stream.unwrap();
+/// Stop the server on SIGINT or SIGTERM, or right away on the second one, and
+/// reload the configuration file on SIGHUP.
+#[cfg(unix)]
+fn watch_signals(
+    stopper: server::Stopper,
+    config: Arc<RwLock<Arc<Config>>>,
+    path: Option<PathBuf>,
+) -> Result<()> {
+    use signal::{Signal, Signals};
+    use std::thread;
 
-        match handle_connection(stream) {
-            Err(e) => eprintln!("{:#?}", e),
-            _ => continue,
+    let mut signals = Signals::install()?;
+    thread::spawn(move || {
+        let mut stopping = false;
+        while let Ok(signal) = signals.wait() {
+            match signal {
+                Signal::Stop if stopping => {
+                    log!(Level::Warn, "stopping now");
+                    process::exit(1);
+                }
+                Signal::Stop => {
+                    log!(Level::Info, "stopping, send the signal again to stop now");
+                    stopping = true;
+                    // Stopping blocks until the accept loops are woken up,
+                    // which must not delay a second signal
+                    let stopper = stopper.clone();
+                    thread::spawn(move || stopper.stop());
+                }
+                Signal::Reload => match &path {
+                    Some(path) => reload(&config, path),
+                    None => log!(Level::Warn, "no configuration file to reload"),
+                },
+            }
         }
+    });
+    Ok(())
+}
+
+/// Replace the configuration with the content of the file at `path`, unless
+/// it is invalid.
+///
+/// The listeners and the workers are kept, as changing them would drop the
+/// connections waiting in the listening sockets.
+#[cfg(unix)]
+fn reload(config: &RwLock<Arc<Config>>, path: &Path) {
+    let reloaded = match Config::load(path) {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            log!(Level::Error, "keeping the current configuration: {}", e);
+            return;
+        }
+    };
+    let mut config = config.write().unwrap_or_else(PoisonError::into_inner);
+    if reloaded.listen != config.listen || reloaded.workers != config.workers {
+        log!(
+            Level::Warn,
+            "changes to 'listen' and 'workers' are applied on restart only"
+        );
     }
+    log::set_level(reloaded.log_level);
+    *config = Arc::new(reloaded);
+    log!(Level::Info, "reloaded {}", path.display());
 }
 
-fn handle_connection(mut stream: TcpStream) -> Result<()> {
-    const BASE_PATH: &str = "/home/gageotd";
-    let request = Message::read(&mut BufReader::new(&mut stream))?;
-    match request.start_line() {
-        StartLine::Request { method, url, .. } => match method {
-            Method::Get => {
-                println!("Request: {:#?}", request);
-                index::generate(BASE_PATH, url)?.write(&mut BufWriter::new(&mut stream))?;
-            }
-            _ => {
-                panic!("Only GET method is allowed for the moment");
-            }
-        },
+fn handle_request(config: &Config, request: &Message) -> Result<Message> {
+    let StartLine::Request { method, url, .. } = request.start_line() else {
+        return Err(Error::MalformedRequestLine(String::from(
+            "expected a request instead of a response",
+        )));
+    };
+    log!(Level::Info, "{} {}", method, url);
+    log!(Level::Debug, "{:#?}", request);
+    let host = request.headers().get_host()?.map(|(host, _)| host);
+    let Some(site) = config.site(host) else {
+        return index::generate_error_page(
+            Status::MisdirectedRequest,
+            format!("No site is served for host '{}'.", host.unwrap_or_default()),
+        );
+    };
+    if *method == Method::Options && url.form() == Form::Asterisk {
+        let mut response = Message::new(Status::Ok, None, Some(Body::from(Vec::new())));
+        response.headers_mut().set_allow(&site_methods(site));
+        return Ok(response);
+    }
+    let path = url.to_path()?;
+    let location = site.location(&path);
+    let mut response = respond(site, location, method, &path, request).unwrap_or_else(|e| {
+        log!(Level::Error, "{}", e);
+        connection::error_response(&e)
+    });
+    let status = response.status().unwrap_or(Status::Ok);
+    if let Some(page) = site.error_pages.get(&status.code()) {
+        response = replace_error_page(response, status, page, &location.options);
+    }
+    for (name, value) in &location.headers {
+        response.headers_mut().set(name, value);
+    }
+    if let Some(cache) = &location.cache {
+        if status.is_success() || status == Status::NotModified {
+            response.headers_mut().set_cache_control(cache);
+        }
+    }
+    Ok(response)
+}
+
+fn respond(
+    site: &Site,
+    location: &Location,
+    method: &Method,
+    path: &Path,
+    request: &Message,
+) -> Result<Message> {
+    if let Some(auth) = &location.auth {
+        if !auth.allows(request.headers()) {
+            let mut response = index::generate_error_page(
+                Status::Unauthorized,
+                String::from("Valid credentials are required to access this resource."),
+            )?;
+            response
+                .headers_mut()
+                .set("WWW-Authenticate", auth.challenge());
+            return Ok(response);
+        }
+    }
+    let options = &location.options;
+    match method {
+        Method::Get => index::generate(&site.root, path, request.headers(), options),
+        Method::Head => index::generate_head(&site.root, path, request.headers(), options),
+        Method::Put if options.uploads => {
+            index::upload(&site.root, path, request.body().as_ref(), options)
+        }
+        Method::Options => index::generate_options(&site.root, path, options),
         _ => {
-            panic!("Received a response instead of a request...");
+            let allowed = index::allowed_methods(&site.root, path, options)?;
+            if allowed.is_empty() {
+                // Nothing to allow a method on, answered like a GET request
+                return index::generate(&site.root, path, request.headers(), options);
+            }
+            Err(Error::MethodNotAllowed(allowed))
+        }
+    }
+}
+
+/// Replace the body of an error response with the page configured for its
+/// status, keeping the generated one if the page can't be read anymore.
+fn replace_error_page(
+    response: Message,
+    status: Status,
+    page: &Path,
+    options: &index::Options,
+) -> Message {
+    let content = match fs::read(page) {
+        Ok(content) => content,
+        Err(e) => {
+            log!(Level::Warn, "couldn't read '{}': {}", page.display(), e);
+            return response;
         }
     };
-    Ok(())
+    let mut headers = response.headers().clone();
+    headers.remove("Transfer-Encoding");
+    headers.set(
+        "Content-Type",
+        options.mime_types.content_type(page, &content[..]),
+    );
+    Message::new(status, Some(headers), Some(Body::from(content)))
+}
+
+/// Return the methods supported by some resource of `site`, as asked by an
+/// `OPTIONS *` request.
+fn site_methods(site: &Site) -> Vec<Method> {
+    let mut methods = vec![Method::Get, Method::Head, Method::Options];
+    if site
+        .locations
+        .iter()
+        .any(|location| location.options.uploads)
+    {
+        methods.push(Method::Put);
+    }
+    methods
 }
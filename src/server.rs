@@ -0,0 +1,362 @@
+pub mod connection;
+pub mod pool;
+
+pub use pool::ThreadPool;
+
+use crate::http::message::ParseOptions;
+use crate::http::{Message, Result};
+use crate::log::Level;
+use std::io::{self, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Default number of pending connections per worker before the accept loop
+/// blocks.
+pub const DEFAULT_QUEUE_SIZE_PER_WORKER: usize = 16;
+
+/// Server settings.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Number of worker threads handling connections
+    pub workers: usize,
+    /// Number of accepted connections that can wait for a worker
+    pub queue_size: usize,
+    /// Time to wait for the next request on an idle persistent connection
+    pub keep_alive_timeout: Duration,
+    /// Time given to a client to send the start line and headers of a
+    /// request, once it started sending it
+    pub header_read_timeout: Duration,
+    /// Time given to a client to send the body of a request
+    pub body_read_timeout: Duration,
+    /// Time given to each write of a response to make progress
+    pub write_timeout: Duration,
+    /// Number of requests served on a connection before closing it
+    pub max_requests_per_connection: usize,
+    /// Time given to open connections to finish serving their request once
+    /// the server is stopped
+    pub shutdown_timeout: Duration,
+    /// How requests are parsed, leniently unless told otherwise
+    pub parsing: ParseOptions,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let workers = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4);
+        Self {
+            workers,
+            queue_size: workers * DEFAULT_QUEUE_SIZE_PER_WORKER,
+            keep_alive_timeout: Duration::from_secs(5),
+            header_read_timeout: Duration::from_secs(10),
+            body_read_timeout: Duration::from_secs(60),
+            write_timeout: Duration::from_secs(30),
+            max_requests_per_connection: 100,
+            shutdown_timeout: Duration::from_secs(30),
+            parsing: ParseOptions::default(),
+        }
+    }
+}
+
+/// HTTP server accepting connections on the calling thread and handling them
+/// on a fixed-size pool of workers.
+pub struct Server {
+    listeners: Vec<TcpListener>,
+    pool: ThreadPool,
+    config: Arc<Config>,
+    stopping: Arc<AtomicBool>,
+    connections: Arc<Connections>,
+}
+
+/// Number of connections accepted and not closed yet, signaled whenever one
+/// is closed.
+#[derive(Default)]
+struct Connections {
+    count: Mutex<usize>,
+    closed: Condvar,
+}
+
+/// Token of an open connection, closing it in `Connections` when dropped,
+/// even if its handler panicked.
+struct OpenConnection(Arc<Connections>);
+
+impl OpenConnection {
+    fn new(connections: &Arc<Connections>) -> Self {
+        *connections
+            .count
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) += 1;
+        Self(Arc::clone(connections))
+    }
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap_or_else(PoisonError::into_inner) -= 1;
+        self.0.closed.notify_all();
+    }
+}
+
+/// Handle stopping a running server from another thread.
+#[derive(Debug, Clone)]
+pub struct Stopper {
+    stopping: Arc<AtomicBool>,
+    addresses: Vec<SocketAddr>,
+}
+
+impl Stopper {
+    /// Stop accepting connections, and close the open ones once they have
+    /// answered the request they are serving.
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        // Accept loops are blocked until a client connects, so connect to
+        // each of them to let them see the server is stopping
+        for address in &self.addresses {
+            let mut address = *address;
+            if address.ip().is_unspecified() {
+                address.set_ip(match address.ip() {
+                    IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                    IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+                });
+            }
+            TcpStream::connect_timeout(&address, Duration::from_secs(1)).ok();
+        }
+    }
+}
+
+impl Server {
+    /// Bind a listener to every address `addresses` resolves to, such as both
+    /// loopback addresses for `localhost:8080`.
+    pub fn bind(addresses: impl ToSocketAddrs, config: Config) -> Result<Self> {
+        let listeners = addresses
+            .to_socket_addrs()?
+            .map(TcpListener::bind)
+            .collect::<io::Result<Vec<_>>>()?;
+        if listeners.is_empty() {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "no address to bind to").into());
+        }
+        Ok(Self {
+            listeners,
+            pool: ThreadPool::new(config.workers, config.queue_size),
+            config: Arc::new(config),
+            stopping: Arc::new(AtomicBool::new(false)),
+            connections: Arc::new(Connections::default()),
+        })
+    }
+
+    /// Return the address of the first listener.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listeners[0].local_addr()?)
+    }
+
+    pub fn local_addrs(&self) -> Result<Vec<SocketAddr>> {
+        Ok(self
+            .listeners
+            .iter()
+            .map(TcpListener::local_addr)
+            .collect::<io::Result<_>>()?)
+    }
+
+    /// Return a handle stopping the server, which can be sent to another
+    /// thread.
+    pub fn stopper(&self) -> Result<Stopper> {
+        Ok(Stopper {
+            stopping: Arc::clone(&self.stopping),
+            addresses: self.local_addrs()?,
+        })
+    }
+
+    /// Accept connections until the server is stopped, answering each request
+    /// they carry with `handler` on a worker thread.
+    ///
+    /// Each listener has its own accept loop, all of them sharing the same
+    /// pool. An accept loop blocks while the queue is full, leaving further
+    /// clients waiting in the listening socket backlog.
+    ///
+    /// Once stopped, waits for the open connections to be closed for at most
+    /// the shutdown timeout, and returns whether they all were. Dropping the
+    /// server waits for the remaining ones, so exit the process instead to cut
+    /// them.
+    pub fn run<F>(&self, handler: F) -> bool
+    where
+        F: Fn(&Message) -> Result<Message> + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        thread::scope(|scope| {
+            for listener in &self.listeners {
+                let handler = &handler;
+                scope.spawn(move || self.accept(listener, handler));
+            }
+        });
+        let count = self
+            .connections
+            .count
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if *count > 0 {
+            log!(
+                Level::Info,
+                "waiting for the open connections to close ({} left)",
+                *count
+            );
+        }
+        let (count, _) = self
+            .connections
+            .closed
+            .wait_timeout_while(count, self.config.shutdown_timeout, |count| *count > 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        *count == 0
+    }
+
+    fn accept<F>(&self, listener: &TcpListener, handler: &Arc<F>)
+    where
+        F: Fn(&Message) -> Result<Message> + Send + Sync + 'static,
+    {
+        for stream in listener.incoming() {
+            if self.stopping.load(Ordering::SeqCst) {
+                break;
+            }
+            match stream {
+                Ok(stream) => {
+                    let handler = Arc::clone(handler);
+                    let config = Arc::clone(&self.config);
+                    let stopping = Arc::clone(&self.stopping);
+                    let open = OpenConnection::new(&self.connections);
+                    self.pool.execute(move || {
+                        let _open = open;
+                        if let Err(e) =
+                            connection::handle(stream, &config, handler.as_ref(), &stopping)
+                        {
+                            log!(Level::Warn, "{}", e);
+                        }
+                    });
+                }
+                Err(e) => log!(Level::Error, "{}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Status;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn test_run_dispatches_connections_to_handler() {
+        let server = Server::bind(
+            "127.0.0.1:0",
+            Config {
+                workers: 2,
+                queue_size: 2,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || {
+            server.run(|_| Ok(Message::new(Status::Ok, None, Some("hello".parse()?))))
+        });
+        for _ in 0..4 {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            let mut buffer = String::new();
+            stream.read_to_string(&mut buffer).unwrap();
+            assert!(buffer.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(buffer.ends_with("\r\n\r\nhello"));
+        }
+    }
+
+    #[test]
+    fn test_run_accepts_on_every_listener() {
+        let server = Server::bind(
+            &[
+                "127.0.0.1:0".parse().unwrap(),
+                "127.0.0.1:0".parse().unwrap(),
+            ][..],
+            Config {
+                workers: 1,
+                ..Config::default()
+            },
+        )
+        .unwrap();
+        let addresses = server.local_addrs().unwrap();
+        assert_eq!(2, addresses.len());
+        assert_eq!(addresses[0], server.local_addr().unwrap());
+        thread::spawn(move || {
+            server.run(|_| Ok(Message::new(Status::Ok, None, Some("hello".parse()?))))
+        });
+        for address in addresses {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            let mut buffer = String::new();
+            stream.read_to_string(&mut buffer).unwrap();
+            assert!(buffer.starts_with("HTTP/1.1 200 OK\r\n"));
+        }
+    }
+
+    #[test]
+    fn test_stop_lets_requests_finish() {
+        let server = Server::bind("127.0.0.1:0", Config::default()).unwrap();
+        let address = server.local_addr().unwrap();
+        let stopper = server.stopper().unwrap();
+        let running = thread::spawn(move || {
+            server.run(|_| {
+                thread::sleep(Duration::from_millis(200));
+                Ok(Message::new(Status::Ok, None, Some("hello".parse()?)))
+            })
+        });
+        let mut idle = TcpStream::connect(address).unwrap();
+        let mut busy = TcpStream::connect(address).unwrap();
+        busy.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        stopper.stop();
+        assert!(running.join().unwrap());
+
+        let mut buffer = String::new();
+        busy.read_to_string(&mut buffer).unwrap();
+        assert!(buffer.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(buffer.contains("Connection: close\r\n"));
+        buffer.clear();
+        idle.read_to_string(&mut buffer).unwrap();
+        assert!(buffer.is_empty());
+        assert!(TcpStream::connect(address).is_err());
+    }
+
+    #[test]
+    fn test_stop_gives_up_after_shutdown_timeout() {
+        let server = Server::bind(
+            "127.0.0.1:0",
+            Config {
+                shutdown_timeout: Duration::from_millis(50),
+                ..Config::default()
+            },
+        )
+        .unwrap();
+        let address = server.local_addr().unwrap();
+        let stopper = server.stopper().unwrap();
+        let running = thread::spawn(move || {
+            let served = server.run(|_| {
+                thread::sleep(Duration::from_millis(500));
+                Ok(Message::new(Status::Ok, None, None))
+            });
+            // Dropping the server would wait for the connection
+            std::mem::forget(server);
+            served
+        });
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        stopper.stop();
+        assert!(!running.join().unwrap());
+    }
+}
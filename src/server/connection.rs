@@ -0,0 +1,412 @@
+use super::Config;
+use crate::http::{index, Body, Error, Message, Method, Result, StartLine, Version};
+use crate::log::Level;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Interval at which an idle connection checks whether the server is
+/// stopping.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reader failing with `TimedOut` once its deadline is passed.
+///
+/// A read timeout on the socket alone only bounds the time between two reads,
+/// which a client sending a byte every few seconds never reaches.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Option<Instant>,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(deadline) = self.deadline {
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| !remaining.is_zero())
+                .ok_or(io::Error::from(ErrorKind::TimedOut))?;
+            self.stream.set_read_timeout(Some(remaining))?;
+        }
+        self.stream.read(buf)
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Serve requests from `stream` with `handler` until the connection is closed
+/// by either side.
+///
+/// Requests are read one after the other from the same buffered reader, so
+/// pipelined requests are answered in order. Responses are only flushed once
+/// no pipelined request is left in the buffer.
+///
+/// Once `stopping` is set, the connection is closed after the response to the
+/// request being served, or right away if it is idle.
+pub fn handle<F>(
+    stream: TcpStream,
+    config: &Config,
+    handler: &F,
+    stopping: &AtomicBool,
+) -> Result<()>
+where
+    F: Fn(&Message) -> Result<Message>,
+{
+    let mut reader = BufReader::new(DeadlineReader {
+        stream: &stream,
+        deadline: None,
+    });
+    let mut writer = BufWriter::new(&stream);
+    stream.set_write_timeout(Some(config.write_timeout))?;
+    let mut served = 0;
+    'requests: loop {
+        // Wait for the next request for at most the keep-alive timeout, unless
+        // the server stops in the meantime
+        let idle_deadline = Instant::now() + config.keep_alive_timeout;
+        loop {
+            let deadline = idle_deadline.min(Instant::now() + STOP_POLL_INTERVAL);
+            reader.get_mut().deadline = Some(deadline);
+            match reader.fill_buf() {
+                Ok([]) => break 'requests,
+                Ok(_) => break,
+                Err(e) if is_timeout(&e) => {
+                    if deadline == idle_deadline || stopping.load(Ordering::SeqCst) {
+                        break 'requests;
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        reader.get_mut().deadline = Some(Instant::now() + config.header_read_timeout);
+        let request =
+            Message::read_head_with(&mut reader, &config.parsing).and_then(|mut request| {
+                reader.get_mut().deadline = Some(Instant::now() + config.body_read_timeout);
+                request.read_body_with(&mut reader, &config.parsing)?;
+                Ok(request)
+            });
+        let request = match request {
+            Ok(request) => request,
+            Err(e) => {
+                let e = match e {
+                    Error::Io(e) if is_timeout(&e) => {
+                        Error::RequestTimeout("while reading the request".to_string())
+                    }
+                    e => e,
+                };
+                // The end of the faulty request is unknown, so the connection
+                // can't be used for another one
+                let mut response = error_response(&e);
+                response.headers_mut().set("Connection", "close");
+                response.write(&mut writer).ok();
+                writer.flush().ok();
+                return Err(e);
+            }
+        };
+        served += 1;
+        // A request framed by both Content-Length and Transfer-Encoding may have
+        // been framed differently by an intermediary, so the connection can't
+        // be trusted for another one
+        let ambiguous = request.headers().contains("Content-Length")
+            && request.headers().contains("Transfer-Encoding");
+        let mut keep_alive =
+            request.keep_alive() && !ambiguous && served < config.max_requests_per_connection;
+        let mut response = handler(&request).unwrap_or_else(|e| {
+            log!(Level::Error, "{}", e);
+            error_response(&e)
+        });
+        // Checked once the response is ready, as the server may have been
+        // stopped while the handler was running
+        keep_alive &= !stopping.load(Ordering::SeqCst);
+        if let StartLine::Request {
+            method: Method::Head,
+            ..
+        } = request.start_line()
+        {
+            // The headers describe the body a GET request would get, which
+            // must not be sent
+            response.take_body();
+        }
+        if response.headers().is_chunked() && *request.version() == Version::V1_0 {
+            // HTTP/1.0 clients don't know about chunked transfer coding, so
+            // the end of the body is signaled by closing the connection
+            response.headers_mut().remove("Transfer-Encoding");
+            keep_alive = false;
+        }
+        if !keep_alive {
+            response.headers_mut().set("Connection", "close");
+        } else if *request.version() == Version::V1_0 {
+            response.headers_mut().set("Connection", "keep-alive");
+        }
+        response.write(&mut writer)?;
+        if !keep_alive {
+            break;
+        }
+        if reader.buffer().is_empty() {
+            writer.flush()?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Build the response to a request that failed with `error`.
+///
+/// Details are only given for client errors, so that server internals don't
+/// leak to clients.
+pub fn error_response(error: &Error) -> Message {
+    let status = error.status();
+    let description = if status.is_client_error() {
+        format!("The request could not be served: {}.", error)
+    } else {
+        String::from("The server encountered an error while serving the request.")
+    };
+    let mut response = index::generate_error_page(status, description)
+        .unwrap_or_else(|_| Message::new(status, None, Some(Body::from(Vec::new()))));
+    if let Error::MethodNotAllowed(allowed) = error {
+        response.headers_mut().set_allow(allowed);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::message::ParseOptions;
+    use crate::http::Status;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    fn setup_connection(config: Config) -> TcpStream {
+        setup_connection_with_handler(config, |_| {
+            Ok(Message::new(Status::Ok, None, Some("hi".parse()?)))
+        })
+    }
+
+    fn setup_connection_with_handler<F>(config: Config, handler: F) -> TcpStream
+    where
+        F: Fn(&Message) -> Result<Message> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle(stream, &config, &handler, &AtomicBool::new(false)).ok();
+        });
+        TcpStream::connect(address).unwrap()
+    }
+
+    fn send(stream: &mut TcpStream, requests: &str) -> String {
+        stream.write_all(requests.as_bytes()).unwrap();
+        let mut buffer = String::new();
+        stream.read_to_string(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_handle_pipelined_requests_in_order() {
+        let mut stream = setup_connection(Config::default());
+        let response = send(
+            &mut stream,
+            "GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n\r\nGET /c HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(3, response.matches("HTTP/1.1 200 OK").count());
+        assert_eq!(1, response.matches("Connection: close").count());
+        assert!(response.ends_with("Connection: close\r\nContent-Length: 2\r\n\r\nhi"));
+    }
+
+    #[test]
+    fn test_handle_head_without_body() {
+        let mut stream = setup_connection(Config::default());
+        let response = send(
+            &mut stream,
+            "HEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n\
+             HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nhi",
+            response
+        );
+    }
+
+    #[test]
+    fn test_handle_close_by_default_on_http_1_0() {
+        let mut stream = setup_connection(Config::default());
+        let response = send(&mut stream, "GET / HTTP/1.0\r\n\r\nGET / HTTP/1.0\r\n\r\n");
+        assert_eq!(1, response.matches("HTTP/1.1 200 OK").count());
+        assert!(response.contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn test_handle_keep_alive_on_http_1_0() {
+        let mut stream = setup_connection(Config::default());
+        let response = send(
+            &mut stream,
+            "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGET / HTTP/1.0\r\n\r\n",
+        );
+        assert_eq!(2, response.matches("HTTP/1.1 200 OK").count());
+        assert!(response.contains("Connection: keep-alive\r\n"));
+    }
+
+    #[test]
+    fn test_handle_close_after_max_requests() {
+        let mut stream = setup_connection(Config {
+            max_requests_per_connection: 2,
+            ..Config::default()
+        });
+        let response = send(&mut stream, &"GET / HTTP/1.1\r\n\r\n".repeat(3));
+        assert_eq!(2, response.matches("HTTP/1.1 200 OK").count());
+        assert_eq!(1, response.matches("Connection: close").count());
+    }
+
+    #[test]
+    fn test_handle_malformed_request() {
+        let mut stream = setup_connection(Config::default());
+        let response = send(
+            &mut stream,
+            "GET / HTTP/1.1\r\nNo colon\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.contains("Connection: close\r\n"));
+        assert!(!response.contains("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn test_handle_unknown_method() {
+        let mut stream = setup_connection(Config::default());
+        let response = send(&mut stream, "BREW / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+    }
+
+    #[test]
+    fn test_handle_close_after_ambiguous_framing() {
+        let mut stream = setup_connection(Config::default());
+        let response = send(
+            &mut stream,
+            "POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+        );
+        assert_eq!(1, response.matches("HTTP/1.1 200 OK").count());
+        assert!(response.contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn test_handle_strict_parsing() {
+        let mut stream = setup_connection(Config {
+            parsing: ParseOptions {
+                strict: true,
+                ..ParseOptions::default()
+            },
+            ..Config::default()
+        });
+        let response = send(
+            &mut stream,
+            "GET / HTTP/1.1\r\nHost: localhost\r\n\r\nGET / HTTP/1.1\r\n\r\n",
+        );
+        let (first, second) = response.split_at(response.find("HTTP/1.1 400").unwrap());
+        assert!(first.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(second.contains("missing Host header"));
+    }
+
+    #[test]
+    fn test_handle_request_too_large() {
+        let config = Config {
+            parsing: ParseOptions {
+                max_start_line_length: 32,
+                ..ParseOptions::default()
+            },
+            ..Config::default()
+        };
+        let mut stream = setup_connection(config.clone());
+        let response = send(
+            &mut stream,
+            &format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(32)),
+        );
+        assert!(response.starts_with("HTTP/1.1 414 URI Too Long\r\n"));
+        for length in ["99999999999", "99999999999999999999999"] {
+            let mut stream = setup_connection(config.clone());
+            let response = send(
+                &mut stream,
+                &format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", length),
+            );
+            assert!(response.starts_with("HTTP/1.1 413 Content Too Large\r\n"));
+            assert!(response.contains("Connection: close\r\n"));
+        }
+    }
+
+    #[test]
+    fn test_handle_handler_errors() {
+        let mut stream = setup_connection_with_handler(Config::default(), |request| match request
+            .start_line()
+        {
+            StartLine::Request {
+                method: Method::Get,
+                ..
+            } => Err(Error::Io(std::io::Error::other("secret"))),
+            _ => Err(Error::MethodNotAllowed(vec![Method::Get, Method::Head])),
+        });
+        let response = send(
+            &mut stream,
+            "GET / HTTP/1.1\r\n\r\nPUT / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        let (first, second) = response.split_at(response.find("HTTP/1.1 405").unwrap());
+        assert!(first.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert!(!first.contains("secret"));
+        assert!(second.contains("Allow: GET, HEAD\r\n"));
+    }
+
+    #[test]
+    fn test_handle_request_timeout() {
+        let mut stream = setup_connection(Config {
+            header_read_timeout: Duration::from_millis(50),
+            ..Config::default()
+        });
+        let response = send(&mut stream, "GET / HTTP/1.1\r\nHost: local");
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+        assert!(response.contains("Connection: close\r\n"));
+
+        let mut stream = setup_connection(Config {
+            body_read_timeout: Duration::from_millis(50),
+            ..Config::default()
+        });
+        let response = send(
+            &mut stream,
+            "POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhel",
+        );
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    }
+
+    #[test]
+    fn test_deadline_reader_times_out_slow_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        // Send a byte every 10ms, well within a read timeout of 100ms
+        thread::spawn(move || {
+            while client.write_all(b"a").is_ok() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let start = Instant::now();
+        let mut reader = DeadlineReader {
+            stream: &stream,
+            deadline: Some(start + Duration::from_millis(100)),
+        };
+        let error = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+        assert!(is_timeout(&error));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_handle_close_idle_connection() {
+        let mut stream = setup_connection(Config {
+            keep_alive_timeout: Duration::from_millis(50),
+            ..Config::default()
+        });
+        let response = send(&mut stream, "GET / HTTP/1.1\r\n\r\n");
+        assert_eq!(1, response.matches("HTTP/1.1 200 OK").count());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::log::Level;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Fixed-size pool of worker threads fed through a bounded job queue.
+///
+/// When the queue is full, `ThreadPool::execute` blocks until a worker picks up
+/// a job, which propagates backpressure to the caller.
+pub struct ThreadPool {
+    workers: Vec<Worker>,
+    sender: Option<SyncSender<Job>>,
+}
+
+impl ThreadPool {
+    /// Create a pool of `size` workers with room for `queue_size` pending jobs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(size: usize, queue_size: usize) -> Self {
+        assert!(size > 0, "thread pool needs at least one worker");
+        let (sender, receiver) = mpsc::sync_channel(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..size)
+            .map(|id| Worker::new(id, Arc::clone(&receiver)))
+            .collect();
+        Self {
+            workers,
+            sender: Some(sender),
+        }
+    }
+
+    /// Queue a job, blocking while the queue is full.
+    pub fn execute<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if let Some(sender) = &self.sender {
+            // Sending only fails if every worker is gone, which cannot happen
+            // before the pool is dropped.
+            sender.send(Box::new(f)).ok();
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // Closing the channel makes every worker leave its loop once the queue
+        // is drained
+        drop(self.sender.take());
+        for worker in &mut self.workers {
+            if let Some(thread) = worker.thread.take() {
+                thread.join().ok();
+            }
+        }
+    }
+}
+
+struct Worker {
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Worker {
+    fn new(id: usize, receiver: Arc<Mutex<Receiver<Job>>>) -> Self {
+        let thread = thread::Builder::new()
+            .name(format!("worker-{}", id))
+            .spawn(move || loop {
+                let job = match receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => break,
+                };
+                match job {
+                    // A panicking job must not take the worker down with it
+                    Ok(job) => {
+                        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                            log!(Level::Error, "job panicked on worker {}", id);
+                        }
+                    }
+                    Err(_) => break,
+                }
+            })
+            .expect("failed to spawn worker thread");
+        Self {
+            thread: Some(thread),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+
+    #[test]
+    fn test_execute_runs_every_job() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        {
+            let pool = ThreadPool::new(4, 2);
+            for _ in 0..32 {
+                let counter = Arc::clone(&counter);
+                pool.execute(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        }
+        assert_eq!(32, counter.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_execute_runs_jobs_concurrently() {
+        let pool = ThreadPool::new(2, 0);
+        let barrier = Arc::new(Barrier::new(3));
+        for _ in 0..2 {
+            let barrier = Arc::clone(&barrier);
+            pool.execute(move || {
+                barrier.wait();
+            });
+        }
+        // Would deadlock if both jobs did not run at the same time
+        barrier.wait();
+    }
+
+    #[test]
+    fn test_execute_survives_panicking_jobs() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        {
+            let pool = ThreadPool::new(1, 1);
+            pool.execute(|| panic!("job panicked on purpose"));
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        assert_eq!(1, counter.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(3, ThreadPool::new(3, 1).size());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one worker")]
+    fn test_new_panic_if_size_is_zero() {
+        ThreadPool::new(0, 1);
+    }
+}
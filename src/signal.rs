@@ -0,0 +1,95 @@
+//! Signals asking the server to stop or to reload its configuration.
+//!
+//! A signal handler may only call a few async-signal-safe functions, so it
+//! writes the number of the signal to a socket, which a regular thread reads
+//! it from.
+
+use std::io::{self, Read};
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+const SIGHUP: c_int = 1;
+const SIGINT: c_int = 2;
+const SIGTERM: c_int = 15;
+const SIG_ERR: usize = usize::MAX;
+
+extern "C" {
+    fn signal(signum: c_int, handler: usize) -> usize;
+    fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+}
+
+/// Socket the handler writes the signals it receives to.
+static SIGNAL_FD: AtomicI32 = AtomicI32::new(-1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// SIGINT or SIGTERM
+    Stop,
+    /// SIGHUP
+    Reload,
+}
+
+/// Receiver of the signals sent to the process.
+pub struct Signals(UnixStream);
+
+impl Signals {
+    /// Handle SIGINT, SIGTERM and SIGHUP from now on, instead of letting them
+    /// terminate the process. Only one receiver should be installed.
+    pub fn install() -> io::Result<Self> {
+        let (receiver, sender) = UnixStream::pair()?;
+        // A signal is rather lost than the handler blocked on a full socket
+        sender.set_nonblocking(true)?;
+        SIGNAL_FD.store(sender.into_raw_fd(), Ordering::SeqCst);
+        for signum in [SIGHUP, SIGINT, SIGTERM] {
+            // SAFETY: the handler only calls async-signal-safe functions
+            let previous = unsafe { signal(signum, on_signal as extern "C" fn(c_int) as usize) };
+            if previous == SIG_ERR {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(Self(receiver))
+    }
+
+    /// Block until the next signal is received.
+    pub fn wait(&mut self) -> io::Result<Signal> {
+        let mut signum = [0];
+        self.0.read_exact(&mut signum)?;
+        Ok(match c_int::from(signum[0]) {
+            SIGHUP => Signal::Reload,
+            _ => Signal::Stop,
+        })
+    }
+}
+
+extern "C" fn on_signal(signum: c_int) {
+    let byte = signum as u8;
+    // SAFETY: the buffer outlives the call, and a failed write only loses the
+    // signal
+    unsafe {
+        write(
+            SIGNAL_FD.load(Ordering::SeqCst),
+            &byte as *const u8 as *const c_void,
+            1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" {
+        fn raise(signum: c_int) -> c_int;
+    }
+
+    #[test]
+    fn test_wait() {
+        let mut signals = Signals::install().unwrap();
+        for (signum, signal) in [(SIGHUP, Signal::Reload), (SIGTERM, Signal::Stop)] {
+            assert_eq!(0, unsafe { raise(signum) });
+            assert_eq!(signal, signals.wait().unwrap());
+        }
+    }
+}